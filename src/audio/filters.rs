@@ -0,0 +1,49 @@
+use std::f32::consts::PI;
+
+//First order filters, like the RC filters of the NES audio output
+pub struct HighPassFilter {
+    alpha: f32,
+    previous_input: f32,
+    previous_output: f32,
+}
+
+impl HighPassFilter {
+    pub fn new(cutoff: f32, sample_rate: f32) -> HighPassFilter {
+        let rc = 1.0 / (2.0 * PI * cutoff);
+        let dt = 1.0 / sample_rate;
+        HighPassFilter {
+            alpha: rc / (rc + dt),
+            previous_input: 0.0,
+            previous_output: 0.0,
+        }
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let output = self.alpha * (self.previous_output + input - self.previous_input);
+        self.previous_input = input;
+        self.previous_output = output;
+        output
+    }
+}
+
+pub struct LowPassFilter {
+    alpha: f32,
+    previous_output: f32,
+}
+
+impl LowPassFilter {
+    pub fn new(cutoff: f32, sample_rate: f32) -> LowPassFilter {
+        let rc = 1.0 / (2.0 * PI * cutoff);
+        let dt = 1.0 / sample_rate;
+        LowPassFilter {
+            alpha: dt / (rc + dt),
+            previous_output: 0.0,
+        }
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let output = self.previous_output + self.alpha * (input - self.previous_output);
+        self.previous_output = output;
+        output
+    }
+}
@@ -0,0 +1,103 @@
+use crate::audio::filters::{HighPassFilter, LowPassFilter};
+use crate::audio::resampler::Resampler;
+use sdl2::audio::{AudioQueue, AudioSpecDesired};
+use sdl2::AudioSubsystem;
+use std::thread::sleep;
+use std::time::Duration;
+
+mod filters;
+mod resampler;
+
+const SAMPLE_RATE: i32 = 48000;
+//The dynamic rate control keeps this much audio in the queue
+const TARGET_LATENCY_SECONDS: f32 = 0.05;
+//Maximum change of the resampling ratio, small enough to not be heard
+const MAX_RATE_DEVIATION: f32 = 0.005;
+//If the emulation runs too fast for the rate control (e.g. no pacing), the queue is dropped
+const MAX_QUEUED_SECONDS: f32 = 0.25;
+
+//Plays the APU output with SDL, after the filters of the NES audio output
+pub struct AudioOutput {
+    queue: AudioQueue<f32>,
+    resampler: Resampler,
+    high_pass_90: HighPassFilter,
+    high_pass_440: HighPassFilter,
+    low_pass_14k: LowPassFilter,
+    volume: f32,
+    output_rate: f32,
+    buffer: Vec<f32>,
+}
+
+impl AudioOutput {
+    //input_rate is the rate of the APU samples, so the CPU clock
+    pub fn new(
+        audio: &AudioSubsystem,
+        input_rate: f64,
+        volume: f32,
+    ) -> Result<AudioOutput, String> {
+        let desired_spec = AudioSpecDesired {
+            freq: Some(SAMPLE_RATE),
+            channels: Some(1),
+            samples: Some(1024),
+        };
+        let queue: AudioQueue<f32> = audio.open_queue(None, &desired_spec)?;
+        let output_rate = queue.spec().freq as f32;
+        queue.resume();
+
+        Ok(AudioOutput {
+            queue,
+            resampler: Resampler::new(input_rate, output_rate as f64),
+            high_pass_90: HighPassFilter::new(90.0, output_rate),
+            high_pass_440: HighPassFilter::new(440.0, output_rate),
+            low_pass_14k: LowPassFilter::new(14000.0, output_rate),
+            volume,
+            output_rate,
+            buffer: Vec::new(),
+        })
+    }
+
+    fn get_queued_seconds(&self) -> f32 {
+        let queued_samples = self.queue.size() as usize / std::mem::size_of::<f32>();
+        queued_samples as f32 / self.output_rate
+    }
+
+    /*
+    Dynamic rate control (like RetroArch): the resampling ratio is slightly increased when
+    the queue is below the target latency and decreased when it's above, so the emulation
+    and the audio device stay in sync even if they don't run at exactly the same rate.
+     */
+    fn update_rate_control(&mut self) {
+        let target = TARGET_LATENCY_SECONDS;
+        let direction = ((target - self.get_queued_seconds()) / target).clamp(-1.0, 1.0);
+        let adjust = 1.0 + MAX_RATE_DEVIATION * direction;
+        self.resampler.set_rate_adjust(f64::from(adjust));
+    }
+
+    //Audio pacing: blocks until the queue is back to the target latency
+    pub fn wait_for_queue(&self) {
+        while self.get_queued_seconds() > TARGET_LATENCY_SECONDS {
+            sleep(Duration::from_millis(1));
+        }
+    }
+
+    pub fn push_samples(&mut self, samples: &[f32]) {
+        self.update_rate_control();
+        self.buffer.clear();
+        self.resampler.push(samples);
+        self.resampler.read(&mut self.buffer);
+
+        for sample in self.buffer.iter_mut() {
+            let filtered = self.high_pass_90.process(*sample);
+            let filtered = self.high_pass_440.process(filtered);
+            let filtered = self.low_pass_14k.process(filtered);
+            *sample = filtered * self.volume;
+        }
+
+        if self.get_queued_seconds() > MAX_QUEUED_SECONDS {
+            self.queue.clear();
+        }
+        if let Err(error) = self.queue.queue_audio(&self.buffer) {
+            eprintln!("Cannot queue audio: {}", error);
+        }
+    }
+}
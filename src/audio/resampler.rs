@@ -0,0 +1,114 @@
+use std::f64::consts::PI;
+
+//Half width of the band-limited impulse, in output samples
+const HALF_WIDTH: usize = 16;
+const KERNEL_WIDTH: usize = HALF_WIDTH * 2;
+//Number of precomputed sub-sample positions of the impulse
+const PHASES: usize = 64;
+//Cutoff frequency, relative to the output sample rate (Nyquist is 0.5)
+const CUTOFF: f64 = 0.45;
+
+/*
+Band-limited resampler, same idea of blip_buf.
+The APU output is a sum of steps, so instead of filtering every input sample, each change
+of level adds a band-limited impulse (windowed sinc) to a buffer of deltas, at its exact
+position in output samples. Integrating the deltas gives the output samples.
+The output is delayed by HALF_WIDTH samples.
+ */
+pub struct Resampler {
+    kernels: Vec<[f32; KERNEL_WIDTH]>,
+    deltas: Vec<f32>,
+    //Position of the next input sample, in output samples from the start of deltas
+    time: f64,
+    //Output samples per input sample
+    base_ratio: f64,
+    ratio: f64,
+    last_input: f32,
+    integrator: f32,
+}
+
+//Impulse centered on HALF_WIDTH + offset, offset is between 0.0 and 1.0
+fn create_kernel(offset: f64) -> [f32; KERNEL_WIDTH] {
+    let mut kernel = [0.0; KERNEL_WIDTH];
+    let mut sum = 0.0;
+    for (i, tap) in kernel.iter_mut().enumerate() {
+        let x = i as f64 - HALF_WIDTH as f64 - offset;
+        let sinc = if x == 0.0 {
+            2.0 * CUTOFF
+        } else {
+            (2.0 * PI * CUTOFF * x).sin() / (PI * x)
+        };
+        //Blackman window over the whole kernel
+        let position = (x + HALF_WIDTH as f64) / KERNEL_WIDTH as f64;
+        let window = 0.42 - 0.5 * (2.0 * PI * position).cos() + 0.08 * (4.0 * PI * position).cos();
+        let value = sinc * window.max(0.0);
+        *tap = value as f32;
+        sum += value;
+    }
+
+    //Each impulse must sum to 1, or the steps wouldn't reach the right level
+    for tap in kernel.iter_mut() {
+        *tap = (*tap as f64 / sum) as f32;
+    }
+    kernel
+}
+
+impl Resampler {
+    pub fn new(input_rate: f64, output_rate: f64) -> Resampler {
+        Resampler {
+            kernels: (0..PHASES)
+                .map(|phase| create_kernel(phase as f64 / PHASES as f64))
+                .collect(),
+            deltas: vec![0.0; KERNEL_WIDTH + 1],
+            time: 0.0,
+            base_ratio: output_rate / input_rate,
+            ratio: output_rate / input_rate,
+            last_input: 0.0,
+            integrator: 0.0,
+        }
+    }
+
+    //Scales the output rate, for the dynamic rate control
+    pub fn set_rate_adjust(&mut self, adjust: f64) {
+        self.ratio = self.base_ratio * adjust;
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        let needed = (self.time + samples.len() as f64 * self.ratio) as usize + KERNEL_WIDTH + 1;
+        if self.deltas.len() < needed {
+            self.deltas.resize(needed, 0.0);
+        }
+
+        for &sample in samples {
+            if sample != self.last_input {
+                self.add_delta(sample - self.last_input);
+                self.last_input = sample;
+            }
+            self.time += self.ratio;
+        }
+    }
+
+    fn add_delta(&mut self, delta: f32) {
+        let position = self.time.floor();
+        let phase = ((self.time - position) * PHASES as f64) as usize;
+        let start = position as usize;
+
+        let kernel = &self.kernels[phase];
+        for (output, tap) in self.deltas[start..start + KERNEL_WIDTH]
+            .iter_mut()
+            .zip(kernel)
+        {
+            *output += delta * tap;
+        }
+    }
+
+    //Appends the output samples that can't be changed by the next input samples
+    pub fn read(&mut self, output: &mut Vec<f32>) {
+        let available = self.time as usize;
+        for delta in self.deltas.drain(..available) {
+            self.integrator += delta;
+            output.push(self.integrator);
+        }
+        self.time -= available as f64;
+    }
+}
@@ -0,0 +1,63 @@
+use crate::nes::Nes;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+//Battery backed RAM of the cartridge, stored in <rom>.sav next to the ROM file
+pub struct BatterySave {
+    path: PathBuf,
+    last_saved: Vec<u8>,
+}
+
+impl BatterySave {
+    //Loads the save file (if any) into the cartridge. Returns None if the cartridge has no battery
+    pub fn load(rom_path: &str, nes: &mut Nes) -> Option<BatterySave> {
+        let empty_ram = nes.export_battery_ram()?;
+        let path = Path::new(rom_path).with_extension("sav");
+
+        let last_saved = match fs::read(&path) {
+            Ok(data) => {
+                if data.len() != empty_ram.len() {
+                    eprintln!(
+                        "Save file {} is {} bytes, expected {}",
+                        path.display(),
+                        data.len(),
+                        empty_ram.len()
+                    );
+                }
+                nes.import_battery_ram(&data);
+                nes.export_battery_ram().unwrap_or(empty_ram)
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => empty_ram,
+            Err(error) => {
+                eprintln!("Cannot read save file {}: {}", path.display(), error);
+                empty_ram
+            }
+        };
+
+        Some(BatterySave { path, last_saved })
+    }
+
+    //Writes the save file, only if the battery backed RAM changed since the last write
+    pub fn flush(&mut self, nes: &mut Nes) {
+        let ram = match nes.export_battery_ram() {
+            Some(ram) => ram,
+            None => return,
+        };
+        if ram == self.last_saved {
+            return;
+        }
+
+        match self.write(&ram) {
+            Ok(()) => self.last_saved = ram,
+            Err(error) => eprintln!("Cannot write save file {}: {}", self.path.display(), error),
+        }
+    }
+
+    //The data is written in a temporary file first, so a crash can't leave a truncated save
+    fn write(&self, ram: &[u8]) -> io::Result<()> {
+        let tmp_path = self.path.with_extension("sav.tmp");
+        fs::write(&tmp_path, ram)?;
+        fs::rename(&tmp_path, &self.path)
+    }
+}
@@ -0,0 +1,178 @@
+use crate::cartridge::{CartridgeMirroring, RomError};
+
+pub const INES_MAGIC: [u8; 4] = [0x4E, 0x45, 0x53, 0x1A];
+pub const INES_HEADER_SIZE: usize = 16;
+pub const INES_TRAINER_SIZE: usize = 512;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CartridgeTiming {
+    NTSC,
+    PAL,
+    MultipleRegion,
+    Dendy,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ConsoleType {
+    NES,
+    VsSystem,
+    Playchoice10,
+    //Extended console type, from byte 13 of the NES 2.0 header
+    Extended(u8),
+}
+
+#[derive(Clone, Debug)]
+pub struct CartridgeHeader {
+    pub is_nes2: bool,
+    pub mapper: u16,
+    pub submapper: u8,
+    pub pkg_rom_size: usize,
+    pub chr_rom_size: usize,
+    pub pkg_ram_size: usize,
+    pub pkg_nvram_size: usize,
+    pub chr_ram_size: usize,
+    pub chr_nvram_size: usize,
+    pub mirroring: CartridgeMirroring,
+    pub four_screen: bool,
+    pub has_trainer: bool,
+    pub has_battery: bool,
+    pub timing: CartridgeTiming,
+    pub console_type: ConsoleType,
+    pub expansion_device: u8,
+}
+
+/*
+Byte 0-3: "NES" followed by MS-DOS end-of-file
+Byte 4: PRG-ROM size LSB (16 KiB units)
+Byte 5: CHR-ROM size LSB (8 KiB units)
+Byte 6: Flags 6 -> mapper D0..D3, four-screen, trainer, battery, mirroring
+Byte 7: Flags 7 -> mapper D4..D7, NES 2.0 identifier, console type
+Byte 8: (NES 2.0) mapper D8..D11 and submapper
+Byte 9: (NES 2.0) PRG-ROM/CHR-ROM size MSB
+Byte 10: (NES 2.0) PRG-RAM/PRG-NVRAM size shift
+Byte 11: (NES 2.0) CHR-RAM/CHR-NVRAM size shift
+Byte 12: (NES 2.0) CPU/PPU timing
+Byte 13: (NES 2.0) Vs. System type or extended console type
+Byte 14: (NES 2.0) miscellaneous ROMs
+Byte 15: (NES 2.0) default expansion device
+ */
+pub fn parse_ines_header(rom: &Vec<u8>) -> Result<CartridgeHeader, RomError> {
+    if rom.len() < INES_MAGIC.len() || rom[0..4] != INES_MAGIC {
+        return Err(RomError::MissingMagic);
+    }
+    if rom.len() < INES_HEADER_SIZE {
+        return Err(RomError::TruncatedHeader);
+    }
+
+    let flag6 = rom[6];
+    let flag7 = rom[7];
+    let is_nes2 = (flag7 & 0x0C) == 0x08;
+
+    let mirroring = if flag6 & 0x1 == 0 {
+        CartridgeMirroring::HORIZONTAL
+    } else {
+        CartridgeMirroring::VERTICAL
+    };
+    let four_screen = flag6 & 0x8 != 0;
+    let has_trainer = flag6 & 0x4 != 0;
+    let has_battery = flag6 & 0x2 != 0;
+
+    let header = if is_nes2 {
+        let console_type = match flag7 & 0x3 {
+            0 => ConsoleType::NES,
+            1 => ConsoleType::VsSystem,
+            2 => ConsoleType::Playchoice10,
+            _ => ConsoleType::Extended(rom[13] & 0x0F),
+        };
+        let timing = match rom[12] & 0x3 {
+            0 => CartridgeTiming::NTSC,
+            1 => CartridgeTiming::PAL,
+            2 => CartridgeTiming::MultipleRegion,
+            _ => CartridgeTiming::Dendy,
+        };
+
+        CartridgeHeader {
+            is_nes2,
+            mapper: (u16::from(rom[8] & 0x0F) << 8)
+                | u16::from(flag7 & 0xF0)
+                | u16::from(flag6 >> 4),
+            submapper: rom[8] >> 4,
+            pkg_rom_size: get_nes2_rom_size(rom[4], rom[9] & 0x0F, 16384),
+            chr_rom_size: get_nes2_rom_size(rom[5], rom[9] >> 4, 8192),
+            pkg_ram_size: get_nes2_ram_size(rom[10] & 0x0F),
+            pkg_nvram_size: get_nes2_ram_size(rom[10] >> 4),
+            chr_ram_size: get_nes2_ram_size(rom[11] & 0x0F),
+            chr_nvram_size: get_nes2_ram_size(rom[11] >> 4),
+            mirroring,
+            four_screen,
+            has_trainer,
+            has_battery,
+            timing,
+            console_type,
+            expansion_device: rom[15] & 0x3F,
+        }
+    } else {
+        //Old dumps have garbage (like "DiskDude!") in bytes 7..15, so flags 7 can't be trusted
+        let flag7 = if (flag7 & 0x0C) == 0 && rom[12..16].iter().all(|b| *b == 0) {
+            flag7
+        } else {
+            0
+        };
+        let console_type = match flag7 & 0x3 {
+            1 => ConsoleType::VsSystem,
+            2 => ConsoleType::Playchoice10,
+            _ => ConsoleType::NES,
+        };
+        let chr_rom_size = rom[5] as usize * 8192;
+        //iNES assumes 8KB of PRG RAM when byte 8 is 0, battery backed if flags 6 says so
+        let pkg_ram_size = (rom[8].max(1) as usize) * 0x2000;
+
+        CartridgeHeader {
+            is_nes2,
+            mapper: u16::from((flag7 & 0xF0) | (flag6 >> 4)),
+            submapper: 0,
+            pkg_rom_size: rom[4] as usize * 16384,
+            chr_rom_size,
+            pkg_ram_size: if has_battery { 0 } else { pkg_ram_size },
+            pkg_nvram_size: if has_battery { pkg_ram_size } else { 0 },
+            chr_ram_size: if chr_rom_size == 0 { 0x2000 } else { 0 },
+            chr_nvram_size: 0,
+            mirroring,
+            four_screen,
+            has_trainer,
+            has_battery,
+            timing: if rom[9] & 0x1 == 0 {
+                CartridgeTiming::NTSC
+            } else {
+                CartridgeTiming::PAL
+            },
+            console_type,
+            expansion_device: 0,
+        }
+    };
+
+    if header.pkg_rom_size == 0 {
+        return Err(RomError::BadPkgRomSize(0));
+    }
+    Ok(header)
+}
+
+fn get_nes2_rom_size(lsb: u8, msb: u8, unit: usize) -> usize {
+    if msb == 0xF {
+        //Exponent-multiplier notation: EEEEEEMM -> 2^E * (MM * 2 + 1)
+        let exponent = u32::from(lsb >> 2);
+        let multiplier = u128::from(lsb & 0x3) * 2 + 1;
+        usize::try_from(multiplier << exponent).unwrap_or(usize::MAX)
+    } else {
+        ((usize::from(msb) << 8) | usize::from(lsb)) * unit
+    }
+}
+
+fn get_nes2_ram_size(shift: u8) -> usize {
+    //A shift count of 0 means no RAM, otherwise the size is 64 << shift
+    if shift == 0 {
+        0
+    } else {
+        64 << shift
+    }
+}
@@ -0,0 +1,98 @@
+use crate::cartridge::header::CartridgeHeader;
+use crate::cartridge::{
+    create_chr_memory, read_ines_roms, Cartridge, CartridgeMirroring, RomError,
+};
+
+/*
+AxROM (mapper 7): 32KB switchable PKG_ROM bank, 8KB of CHR_RAM and single screen mirroring.
+Writes in 0x8000 - 0xFFFF:
+7  bit  0
+---- ----
+xxxM xPPP
+   |  |||
+   |  +++- Select 32KB PRG ROM bank for 0x8000 - 0xFFFF
+   +------ Select 1KB VRAM page for all 4 nametables
+ */
+pub struct AxROM {
+    pub pkg_rom: Vec<u8>,
+    pub pkg_rom_size: usize,
+    pub chr_rom: Vec<u8>,
+    pub chr_rom_size: usize,
+    pub namespace_mirroring: CartridgeMirroring,
+    pub bus_conflicts: bool,
+
+    pub pkg_bank: usize,
+
+    pub header: CartridgeHeader,
+}
+
+impl Cartridge for AxROM {
+    fn read_pkg_byte(&mut self, addr: u16) -> u8 {
+        let rom_addr = (self.pkg_bank * 0x8000 + addr as usize) % self.pkg_rom_size;
+        self.pkg_rom[rom_addr]
+    }
+
+    fn write_pkg_byte(&mut self, addr: u16, value: u8) {
+        //AMROM and ANROM have bus conflicts, AOROM doesn't
+        let value = if self.bus_conflicts {
+            value & self.read_pkg_byte(addr)
+        } else {
+            value
+        };
+
+        self.pkg_bank = (value & 0x7) as usize;
+        self.namespace_mirroring = if value & 0x10 == 0 {
+            CartridgeMirroring::SingleScreenLower
+        } else {
+            CartridgeMirroring::SingleScreenUpper
+        };
+    }
+
+    fn read_chr_byte(&mut self, addr: u16) -> u8 {
+        self.chr_rom[addr as usize]
+    }
+
+    fn write_chr_byte(&mut self, addr: u16, value: u8) {
+        //Only CHR_RAM can be written
+        if self.chr_rom_size == 0 {
+            self.chr_rom[addr as usize] = value;
+        }
+    }
+
+    fn read_ram_byte(&mut self, _addr: u16) -> u8 {
+        0
+    }
+
+    fn write_ram_byte(&mut self, _addr: u16, _value: u8) {}
+
+    fn get_namespace_mirrored_address(&mut self, addr: u16) -> u16 {
+        self.namespace_mirroring.get_mirrored_address(addr)
+    }
+
+    fn get_header(&self) -> &CartridgeHeader {
+        &self.header
+    }
+}
+
+pub fn create_axrom_from_rom(
+    header: CartridgeHeader,
+    rom: &Vec<u8>,
+) -> Result<Box<impl Cartridge>, RomError> {
+    let (pkg_rom, chr_rom) = read_ines_roms(&header, rom)?;
+    let pkg_rom_size = pkg_rom.len();
+    let chr_rom_size = chr_rom.len();
+
+    let chr_rom = create_chr_memory(&header, chr_rom);
+
+    Ok(Box::new(AxROM {
+        pkg_rom,
+        pkg_rom_size,
+        chr_rom,
+        chr_rom_size,
+        namespace_mirroring: CartridgeMirroring::SingleScreenLower,
+        //NES 2.0 submapper 2 marks boards with AND-type bus conflicts
+        bus_conflicts: header.submapper == 2,
+        pkg_bank: 0,
+        header,
+    }))
+}
@@ -0,0 +1,92 @@
+use crate::cartridge::header::CartridgeHeader;
+use crate::cartridge::{
+    create_chr_memory, read_ines_roms, Cartridge, CartridgeMirroring, RomError,
+};
+
+/*
+CNROM (mapper 3): PKG_ROM is fixed like NROM, while any write in 0x8000 - 0xFFFF
+selects the 8KB CHR_ROM bank.
+ */
+pub struct CNROM {
+    pub pkg_rom: Vec<u8>,
+    pub pkg_rom_size: usize,
+    pub chr_rom: Vec<u8>,
+    pub chr_rom_size: usize,
+    pub namespace_mirroring: CartridgeMirroring,
+    pub bus_conflicts: bool,
+
+    pub chr_bank: usize,
+
+    pub header: CartridgeHeader,
+}
+
+impl Cartridge for CNROM {
+    fn read_pkg_byte(&mut self, addr: u16) -> u8 {
+        //If ROM only has 1 page, it's mirrored into 0xC000 - 0xFFFF
+        let rom_addr = addr as usize % self.pkg_rom_size.min(0x8000);
+        self.pkg_rom[rom_addr]
+    }
+
+    fn write_pkg_byte(&mut self, addr: u16, value: u8) {
+        //The ROM drives the data bus together with the CPU, so the mapper sees
+        //the AND between the written value and the byte stored at that address
+        let value = if self.bus_conflicts {
+            value & self.read_pkg_byte(addr)
+        } else {
+            value
+        };
+
+        let chr_banks = self.chr_rom.len() / 0x2000;
+        self.chr_bank = value as usize % chr_banks;
+    }
+
+    fn read_chr_byte(&mut self, addr: u16) -> u8 {
+        let chr_addr = self.chr_bank * 0x2000 + addr as usize;
+        self.chr_rom[chr_addr]
+    }
+
+    fn write_chr_byte(&mut self, addr: u16, value: u8) {
+        //Only CHR_RAM can be written
+        if self.chr_rom_size == 0 {
+            let chr_addr = self.chr_bank * 0x2000 + addr as usize;
+            self.chr_rom[chr_addr] = value;
+        }
+    }
+
+    fn read_ram_byte(&mut self, _addr: u16) -> u8 {
+        0
+    }
+
+    fn write_ram_byte(&mut self, _addr: u16, _value: u8) {}
+
+    fn get_namespace_mirrored_address(&mut self, addr: u16) -> u16 {
+        self.namespace_mirroring.get_mirrored_address(addr)
+    }
+
+    fn get_header(&self) -> &CartridgeHeader {
+        &self.header
+    }
+}
+
+pub fn create_cnrom_from_rom(
+    header: CartridgeHeader,
+    rom: &Vec<u8>,
+) -> Result<Box<impl Cartridge>, RomError> {
+    let (pkg_rom, chr_rom) = read_ines_roms(&header, rom)?;
+    let pkg_rom_size = pkg_rom.len();
+    let chr_rom_size = chr_rom.len();
+
+    let chr_rom = create_chr_memory(&header, chr_rom);
+
+    Ok(Box::new(CNROM {
+        pkg_rom,
+        pkg_rom_size,
+        chr_rom,
+        chr_rom_size,
+        namespace_mirroring: header.mirroring,
+        //NES 2.0 submapper 1 marks boards without bus conflicts
+        bus_conflicts: header.submapper != 1,
+        chr_bank: 0,
+        header,
+    }))
+}
@@ -1,4 +1,5 @@
-use crate::cartridge::Cartridge;
+use crate::cartridge::header::CartridgeHeader;
+use crate::cartridge::{create_chr_memory, read_ines_roms, Cartridge, RomError};
 use std::ops::{Add, Mul};
 
 pub struct MMC1 {
@@ -15,6 +16,8 @@ pub struct MMC1 {
     pub pkg_bank: u8,
     pub chr0_bank: u8,
     pub chr1_bank: u8,
+
+    pub header: CartridgeHeader,
 }
 
 impl Cartridge for MMC1 {
@@ -136,11 +139,20 @@ impl Cartridge for MMC1 {
     }
 
     fn read_ram_byte(&mut self, addr: u16) -> u8 {
-        self.ram[addr as usize]
+        //Some boards have no PRG_RAM, or less than 8KB mirrored in 0x6000 - 0x7FFF
+        if self.ram.is_empty() {
+            return 0;
+        }
+        let ram_len = self.ram.len();
+        self.ram[addr as usize % ram_len]
     }
 
     fn write_ram_byte(&mut self, addr: u16, value: u8) {
-        self.ram[addr as usize] = value;
+        if self.ram.is_empty() {
+            return;
+        }
+        let ram_len = self.ram.len();
+        self.ram[addr as usize % ram_len] = value;
     }
 
     fn get_namespace_mirrored_address(&mut self, addr: u16) -> u16 {
@@ -156,35 +168,45 @@ impl Cartridge for MMC1 {
             }
         };
     }
-}
 
-pub fn create_mmc1_from_rom(rom: &Vec<u8>) -> Box<impl Cartridge> {
-    let pkg_rom_size = rom[4] as usize * 16384;
-    let chr_rom_size = rom[5] as usize * 8192;
+    fn get_header(&self) -> &CartridgeHeader {
+        &self.header
+    }
+
+    fn get_battery_ram(&mut self) -> Option<&mut [u8]> {
+        if self.header.has_battery {
+            Some(&mut self.ram)
+        } else {
+            None
+        }
+    }
+}
 
-    let pkg_rom_start_index = 16;
-    let chr_rom_start_index = pkg_rom_start_index + pkg_rom_size;
+pub fn create_mmc1_from_rom(
+    header: CartridgeHeader,
+    rom: &Vec<u8>,
+) -> Result<Box<impl Cartridge>, RomError> {
+    let (pkg_rom, chr_rom) = read_ines_roms(&header, rom)?;
+    let pkg_rom_size = pkg_rom.len();
+    let chr_rom_size = chr_rom.len();
 
-    let pkg_rom = rom[pkg_rom_start_index..pkg_rom_start_index + pkg_rom_size].to_vec();
     //The cartridge could use chr_ram...
-    let chr_rom = if chr_rom_size == 0 {
-        vec![0; 0x2000]
-    } else {
-        rom[chr_rom_start_index..chr_rom_start_index + chr_rom_size].to_vec()
-    };
+    let chr_rom = create_chr_memory(&header, chr_rom);
+    let ram_size = header.pkg_ram_size + header.pkg_nvram_size;
 
-    return Box::new(MMC1 {
+    return Ok(Box::new(MMC1 {
         pkg_rom,
         pkg_rom_size,
         chr_rom,
         chr_rom_size,
         mapper: 1,
-        ram: vec![0; 0x2000],
+        ram: vec![0; ram_size],
         shift_register: 0x10,
         current_shift_loc: 0,
         control_register: 0,
         pkg_bank: 0,
         chr0_bank: 0,
         chr1_bank: 0,
-    });
+        header,
+    }));
 }
@@ -0,0 +1,342 @@
+use crate::cartridge::header::CartridgeHeader;
+use crate::cartridge::{
+    create_chr_memory, read_ines_roms, Cartridge, CartridgeMirroring, RomError,
+};
+
+//A12 has to stay low for a while before a rise clocks the IRQ counter. The MMC3 counts
+//M2 cycles, here we count how many PPU bus accesses have been done with A12 low: this
+//filters the rises between the sprite fetches of 8x16 sprites using both pattern tables
+const A12_LOW_FILTER: u32 = 3;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum MMC3Board {
+    MMC3,
+    //MMC6 (Startropics): 1KB of internal PRG_RAM with its own protection scheme
+    MMC6,
+    //TxSROM (118): nametables are selected by bit 7 of the CHR banks
+    TxSROM,
+    //TQROM (119): CHR banks with bit 6 set select 8KB of CHR_RAM
+    TQROM,
+}
+
+pub struct MMC3 {
+    pub pkg_rom: Vec<u8>,
+    pub pkg_rom_size: usize,
+    pub chr_rom: Vec<u8>,
+    pub chr_rom_size: usize,
+    pub chr_ram: Vec<u8>,
+    pub ram: Vec<u8>,
+    pub board: MMC3Board,
+
+    pub bank_select: u8,
+    pub bank_registers: [u8; 8],
+    pub namespace_mirroring: CartridgeMirroring,
+    pub ram_protect: u8,
+    //iNES dumps can't tell MMC3 from MMC6, so RAM protection is honored only with NES 2.0 headers
+    pub honor_ram_protect: bool,
+
+    pub irq_latch: u8,
+    pub irq_counter: u8,
+    pub irq_reload: bool,
+    pub irq_enabled: bool,
+    pub irq_requested: bool,
+    pub a12_low_count: u32,
+
+    pub header: CartridgeHeader,
+}
+
+impl MMC3 {
+    /*
+    Bank select (0x8000):
+    7  bit  0
+    ---- ----
+    CPMx xRRR
+    |||   |||
+    |||   +++- Bank register to update on next write to Bank Data register
+    ||+------- MMC6 PRG_RAM enable
+    |+-------- PRG_ROM bank mode (0: 0x8000 swappable, 0xC000 fixed to second-last bank;
+    |                             1: 0xC000 swappable, 0x8000 fixed to second-last bank)
+    +--------- CHR A12 inversion (0: two 2KB banks at 0x0000, four 1KB banks at 0x1000;
+                                  1: two 2KB banks at 0x1000, four 1KB banks at 0x0000)
+     */
+    fn get_pkg_bank(&self, slot: usize) -> usize {
+        let banks = self.pkg_rom_size / 0x2000;
+        let second_last_bank = banks - 2;
+        let r6 = self.bank_registers[6] as usize;
+        let r7 = self.bank_registers[7] as usize;
+        let swapped = (self.bank_select & 0x40) != 0;
+
+        let bank = match slot {
+            0 => {
+                if swapped {
+                    second_last_bank
+                } else {
+                    r6
+                }
+            }
+            1 => r7,
+            2 => {
+                if swapped {
+                    r6
+                } else {
+                    second_last_bank
+                }
+            }
+            _ => banks - 1,
+        };
+        bank % banks
+    }
+
+    //Returns the bank register value for the given 1KB slot of the pattern tables
+    fn get_chr_bank(&self, slot: usize) -> u8 {
+        let inverted = (self.bank_select & 0x80) != 0;
+        let slot = if inverted { slot ^ 0x4 } else { slot };
+
+        match slot {
+            //R0 and R1 select 2KB banks, so the low bit comes from the address
+            0 => self.bank_registers[0] & 0xFE,
+            1 => self.bank_registers[0] | 0x01,
+            2 => self.bank_registers[1] & 0xFE,
+            3 => self.bank_registers[1] | 0x01,
+            _ => self.bank_registers[slot - 2],
+        }
+    }
+
+    fn is_tqrom_chr_ram(&self, bank: u8) -> bool {
+        self.board == MMC3Board::TQROM && (bank & 0x40) != 0
+    }
+
+    fn clock_irq_counter(&mut self) {
+        if self.irq_counter == 0 || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+            self.irq_reload = false;
+        } else {
+            self.irq_counter -= 1;
+        }
+
+        if self.irq_counter == 0 && self.irq_enabled {
+            self.irq_requested = true;
+        }
+    }
+
+    //MMC6 has 1KB of RAM in 0x7000 - 0x73FF, mirrored up to 0x7FFF and split in two halves
+    fn get_mmc6_ram_access(&self, addr: u16) -> (bool, bool) {
+        let high_half = (addr & 0x200) != 0;
+        let (read_bit, write_bit) = if high_half {
+            (0x80, 0x40)
+        } else {
+            (0x20, 0x10)
+        };
+        (
+            (self.ram_protect & read_bit) != 0,
+            (self.ram_protect & write_bit) != 0,
+        )
+    }
+}
+
+impl Cartridge for MMC3 {
+    fn read_pkg_byte(&mut self, addr: u16) -> u8 {
+        let slot = (addr as usize >> 13) & 0x3;
+        let rom_addr = self.get_pkg_bank(slot) * 0x2000 + (addr as usize & 0x1FFF);
+        self.pkg_rom[rom_addr]
+    }
+
+    fn write_pkg_byte(&mut self, addr: u16, value: u8) {
+        //Registers are selected by A14, A13 and A0
+        match addr & 0x6001 {
+            0x0000 => self.bank_select = value,
+            0x0001 => {
+                let register = (self.bank_select & 0x7) as usize;
+                self.bank_registers[register] = value;
+            }
+            0x2000 => {
+                //TxSROM takes the nametables from the CHR banks
+                if self.board != MMC3Board::TxSROM {
+                    self.namespace_mirroring = if value & 0x1 == 0 {
+                        CartridgeMirroring::VERTICAL
+                    } else {
+                        CartridgeMirroring::HORIZONTAL
+                    };
+                }
+            }
+            0x2001 => {
+                //MMC6 ignores the protection register while its RAM is disabled
+                if self.board != MMC3Board::MMC6 || (self.bank_select & 0x20) != 0 {
+                    self.ram_protect = value;
+                }
+            }
+            0x4000 => self.irq_latch = value,
+            0x4001 => {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            }
+            0x6000 => {
+                self.irq_enabled = false;
+                self.irq_requested = false;
+            }
+            _ => self.irq_enabled = true,
+        }
+    }
+
+    fn read_chr_byte(&mut self, addr: u16) -> u8 {
+        let bank = self.get_chr_bank((addr as usize >> 10) & 0x7);
+        let bank_addr = addr as usize & 0x3FF;
+
+        if self.is_tqrom_chr_ram(bank) {
+            return self.chr_ram[(bank as usize & 0x7) * 0x400 + bank_addr];
+        }
+        let chr_addr = (bank as usize * 0x400 + bank_addr) % self.chr_rom.len();
+        self.chr_rom[chr_addr]
+    }
+
+    fn write_chr_byte(&mut self, addr: u16, value: u8) {
+        let bank = self.get_chr_bank((addr as usize >> 10) & 0x7);
+        let bank_addr = addr as usize & 0x3FF;
+
+        if self.is_tqrom_chr_ram(bank) {
+            self.chr_ram[(bank as usize & 0x7) * 0x400 + bank_addr] = value;
+        } else if self.chr_rom_size == 0 {
+            let chr_addr = (bank as usize * 0x400 + bank_addr) % self.chr_rom.len();
+            self.chr_rom[chr_addr] = value;
+        }
+    }
+
+    fn read_ram_byte(&mut self, addr: u16) -> u8 {
+        if self.ram.is_empty() {
+            return 0;
+        }
+
+        if self.board == MMC3Board::MMC6 {
+            if addr < 0x1000 || (self.bank_select & 0x20) == 0 {
+                return 0;
+            }
+            let (can_read, _) = self.get_mmc6_ram_access(addr);
+            return if can_read {
+                self.ram[addr as usize & 0x3FF]
+            } else {
+                0
+            };
+        }
+
+        if self.honor_ram_protect && (self.ram_protect & 0x80) == 0 {
+            return 0;
+        }
+        let ram_len = self.ram.len();
+        self.ram[addr as usize % ram_len]
+    }
+
+    fn write_ram_byte(&mut self, addr: u16, value: u8) {
+        if self.ram.is_empty() {
+            return;
+        }
+
+        if self.board == MMC3Board::MMC6 {
+            if addr < 0x1000 || (self.bank_select & 0x20) == 0 {
+                return;
+            }
+            let (_, can_write) = self.get_mmc6_ram_access(addr);
+            if can_write {
+                self.ram[addr as usize & 0x3FF] = value;
+            }
+            return;
+        }
+
+        if self.honor_ram_protect && (self.ram_protect & 0xC0) != 0x80 {
+            return;
+        }
+        let ram_len = self.ram.len();
+        self.ram[addr as usize % ram_len] = value;
+    }
+
+    fn get_namespace_mirrored_address(&mut self, addr: u16) -> u16 {
+        if self.board == MMC3Board::TxSROM {
+            //Bit 7 of the CHR bank for the matching 1KB slot selects the CIRAM page
+            let bank = self.get_chr_bank((addr as usize >> 10) & 0x3);
+            return (u16::from(bank >> 7) << 10) | (addr & 0x3FF);
+        }
+        self.namespace_mirroring.get_mirrored_address(addr)
+    }
+
+    fn get_header(&self) -> &CartridgeHeader {
+        &self.header
+    }
+
+    fn notify_ppu_address(&mut self, addr: u16) {
+        if (addr & 0x1000) == 0 {
+            self.a12_low_count = self.a12_low_count.saturating_add(1);
+            return;
+        }
+
+        //Rising edge of A12
+        if self.a12_low_count >= A12_LOW_FILTER {
+            self.clock_irq_counter();
+        }
+        self.a12_low_count = 0;
+    }
+
+    fn is_irq_requested(&self) -> bool {
+        self.irq_requested
+    }
+
+    fn get_battery_ram(&mut self) -> Option<&mut [u8]> {
+        if self.header.has_battery {
+            Some(&mut self.ram)
+        } else {
+            None
+        }
+    }
+}
+
+pub fn create_mmc3_from_rom(
+    header: CartridgeHeader,
+    rom: &Vec<u8>,
+) -> Result<Box<impl Cartridge>, RomError> {
+    let (pkg_rom, chr_rom) = read_ines_roms(&header, rom)?;
+    let pkg_rom_size = pkg_rom.len();
+    let chr_rom_size = chr_rom.len();
+
+    if pkg_rom_size < 0x4000 || pkg_rom_size % 0x2000 != 0 {
+        return Err(RomError::BadPkgRomSize(pkg_rom_size));
+    }
+
+    let board = match (header.mapper, header.submapper) {
+        (4, 1) => MMC3Board::MMC6,
+        (118, _) => MMC3Board::TxSROM,
+        (119, _) => MMC3Board::TQROM,
+        _ => MMC3Board::MMC3,
+    };
+
+    let ram_size = if board == MMC3Board::MMC6 {
+        0x400
+    } else {
+        header.pkg_ram_size + header.pkg_nvram_size
+    };
+
+    let chr_rom = create_chr_memory(&header, chr_rom);
+
+    Ok(Box::new(MMC3 {
+        pkg_rom,
+        pkg_rom_size,
+        chr_rom,
+        chr_rom_size,
+        chr_ram: if board == MMC3Board::TQROM {
+            vec![0; 0x2000]
+        } else {
+            Vec::new()
+        },
+        ram: vec![0; ram_size],
+        board,
+        bank_select: 0,
+        bank_registers: [0, 2, 4, 5, 6, 7, 0, 1],
+        namespace_mirroring: header.mirroring,
+        ram_protect: 0,
+        honor_ram_protect: header.is_nes2,
+        irq_latch: 0,
+        irq_counter: 0,
+        irq_reload: false,
+        irq_enabled: false,
+        irq_requested: false,
+        a12_low_count: 0,
+        header,
+    }))
+}
@@ -1,2 +1,10 @@
+pub mod axrom;
+pub mod cnrom;
 pub mod mmc1;
+pub mod mmc3;
 pub mod nrom;
+pub mod nsf;
+pub mod uxrom;
+pub mod vrc6;
+pub mod vrc6_audio;
+pub mod vrc_irq;
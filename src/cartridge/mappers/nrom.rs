@@ -1,4 +1,7 @@
-use crate::cartridge::{Cartridge, CartridgeMirroring};
+use crate::cartridge::header::CartridgeHeader;
+use crate::cartridge::{
+    create_chr_memory, read_ines_roms, Cartridge, CartridgeMirroring, RomError,
+};
 
 pub struct NROM {
     pub pkg_rom: Vec<u8>,
@@ -6,6 +9,7 @@ pub struct NROM {
     pub chr_rom: Vec<u8>,
     pub chr_rom_size: usize,
     pub namespace_mirroring: CartridgeMirroring,
+    pub header: CartridgeHeader,
 }
 
 impl Cartridge for NROM {
@@ -37,45 +41,31 @@ impl Cartridge for NROM {
     fn write_ram_byte(&mut self, _addr: u16, _value: u8) {}
 
     fn get_namespace_mirrored_address(&mut self, addr: u16) -> u16 {
-        let base_addr = addr & 0x3FF;
+        self.namespace_mirroring.get_mirrored_address(addr)
+    }
 
-        return match self.namespace_mirroring {
-            CartridgeMirroring::HORIZONTAL => ((addr & 0x800) >> 1) | base_addr,
-            CartridgeMirroring::VERTICAL => (addr & 0x400) | base_addr,
-            _ => {
-                panic!("Mirror not supported")
-            }
-        };
+    fn get_header(&self) -> &CartridgeHeader {
+        &self.header
     }
 }
 
-pub fn create_nrom_from_rom(rom: &Vec<u8>) -> Box<impl Cartridge> {
-    let pkg_rom_size = rom[4] as usize * 16384;
-    let chr_rom_size = rom[5] as usize * 8192;
-    let flag6 = rom[6];
-
-    let pkg_rom_start_index = 16;
-    let chr_rom_start_index = pkg_rom_start_index + pkg_rom_size;
+pub fn create_nrom_from_rom(
+    header: CartridgeHeader,
+    rom: &Vec<u8>,
+) -> Result<Box<impl Cartridge>, RomError> {
+    let (pkg_rom, chr_rom) = read_ines_roms(&header, rom)?;
+    let pkg_rom_size = pkg_rom.len();
+    let chr_rom_size = chr_rom.len();
 
-    let pkg_rom = rom[pkg_rom_start_index..pkg_rom_start_index + pkg_rom_size].to_vec();
     //The cartridge could use chr_ram...
-    let chr_rom = if chr_rom_size != 0 {
-        rom[chr_rom_start_index..chr_rom_start_index + chr_rom_size].to_vec()
-    } else {
-        vec![0; 0x2000]
-    };
-
-    let namespace_mirroring = if flag6 & 0x1 == 0 {
-        CartridgeMirroring::HORIZONTAL
-    } else {
-        CartridgeMirroring::VERTICAL
-    };
+    let chr_rom = create_chr_memory(&header, chr_rom);
 
-    Box::new(NROM {
+    Ok(Box::new(NROM {
         pkg_rom,
         pkg_rom_size,
         chr_rom,
         chr_rom_size,
-        namespace_mirroring,
-    })
+        namespace_mirroring: header.mirroring,
+        header,
+    }))
 }
@@ -0,0 +1,131 @@
+use crate::cartridge::header::{CartridgeHeader, CartridgeTiming, ConsoleType};
+use crate::cartridge::mappers::vrc6_audio::Vrc6Audio;
+use crate::cartridge::nsf::{NsfInfo, NSF_CHIP_VRC6};
+use crate::cartridge::{Cartridge, CartridgeMirroring, ExpansionAudio};
+
+/*
+Cartridge built around the program of a NSF file:
+- 8KB of RAM at 0x6000
+- 0x8000 - 0xFFFF is made of eight 4KB banks, selected by writing 0x5FF8 - 0x5FFF
+- CHR_RAM, for the few tunes that display something
+- the VRC6 audio registers (0x9000 - 0xB002), if the tune uses them
+Without bank switching, the program is loaded at its load address and the banks are fixed
+ */
+pub struct NsfCartridge {
+    pub pkg_rom: Vec<u8>,
+    pub pkg_bank_count: usize,
+    pub pkg_banks: [usize; 8],
+    pub chr_ram: Vec<u8>,
+    pub pkg_ram: [u8; 0x2000],
+    pub vrc6_audio: Option<Vrc6Audio>,
+    pub header: CartridgeHeader,
+}
+
+impl Cartridge for NsfCartridge {
+    fn read_pkg_byte(&mut self, addr: u16) -> u8 {
+        let bank = self.pkg_banks[(addr >> 12) as usize];
+        self.pkg_rom[bank * 0x1000 + (addr as usize & 0xFFF)]
+    }
+
+    fn write_pkg_byte(&mut self, addr: u16, value: u8) {
+        if let Some(audio) = self.vrc6_audio.as_mut() {
+            audio.write_register(0x8000 | addr, value);
+        }
+    }
+
+    fn read_chr_byte(&mut self, addr: u16) -> u8 {
+        self.chr_ram[addr as usize]
+    }
+
+    fn write_chr_byte(&mut self, addr: u16, value: u8) {
+        self.chr_ram[addr as usize] = value;
+    }
+
+    fn read_ram_byte(&mut self, addr: u16) -> u8 {
+        self.pkg_ram[addr as usize]
+    }
+
+    fn write_ram_byte(&mut self, addr: u16, value: u8) {
+        self.pkg_ram[addr as usize] = value;
+    }
+
+    fn get_namespace_mirrored_address(&mut self, addr: u16) -> u16 {
+        self.header.mirroring.get_mirrored_address(addr)
+    }
+
+    fn get_header(&self) -> &CartridgeHeader {
+        &self.header
+    }
+
+    fn write_expansion_byte(&mut self, addr: u16, value: u8) {
+        if addr >= 0x5FF8 {
+            let bank = value as usize % self.pkg_bank_count;
+            self.pkg_banks[(addr - 0x5FF8) as usize] = bank;
+        }
+    }
+
+    fn get_expansion_audio(&mut self) -> Option<&mut dyn ExpansionAudio> {
+        return match self.vrc6_audio.as_mut() {
+            Some(audio) => Some(audio),
+            None => None,
+        };
+    }
+}
+
+pub fn create_nsf_cartridge(info: &NsfInfo, data: &[u8]) -> Box<NsfCartridge> {
+    let (pkg_rom, pkg_banks) = match info.bankswitch {
+        //The data is split in 4KB banks, the first one starts at the offset of the load address
+        Some(banks) => {
+            let padding = (info.load_address & 0xFFF) as usize;
+            let mut pkg_rom = vec![0; padding];
+            pkg_rom.extend_from_slice(data);
+            let size = ((pkg_rom.len() + 0xFFF) & !0xFFF).max(0x1000);
+            pkg_rom.resize(size, 0);
+            let bank_count = size / 0x1000;
+            (pkg_rom, banks.map(|bank| bank as usize % bank_count))
+        }
+        None => {
+            let mut pkg_rom = vec![0; 0x8000];
+            let start = (info.load_address - 0x8000) as usize;
+            let len = data.len().min(0x8000 - start);
+            pkg_rom[start..start + len].copy_from_slice(&data[..len]);
+            (pkg_rom, [0, 1, 2, 3, 4, 5, 6, 7])
+        }
+    };
+    let pkg_rom_size = pkg_rom.len();
+
+    Box::new(NsfCartridge {
+        pkg_bank_count: pkg_rom_size / 0x1000,
+        pkg_rom,
+        pkg_banks,
+        chr_ram: vec![0; 0x2000],
+        pkg_ram: [0; 0x2000],
+        vrc6_audio: if info.expansion_chips & NSF_CHIP_VRC6 != 0 {
+            Some(Vrc6Audio::new())
+        } else {
+            None
+        },
+        header: CartridgeHeader {
+            is_nes2: false,
+            mapper: 0,
+            submapper: 0,
+            pkg_rom_size,
+            chr_rom_size: 0,
+            pkg_ram_size: 0x2000,
+            pkg_nvram_size: 0,
+            chr_ram_size: 0x2000,
+            chr_nvram_size: 0,
+            mirroring: CartridgeMirroring::VERTICAL,
+            four_screen: false,
+            has_trainer: false,
+            has_battery: false,
+            timing: if info.is_pal {
+                CartridgeTiming::PAL
+            } else {
+                CartridgeTiming::NTSC
+            },
+            console_type: ConsoleType::NES,
+            expansion_device: 0,
+        },
+    })
+}
@@ -0,0 +1,117 @@
+use crate::cartridge::header::CartridgeHeader;
+use crate::cartridge::{
+    create_chr_memory, read_ines_roms, Cartridge, CartridgeMirroring, RomError,
+};
+
+/*
+UxROM (mapper 2) and its variants:
+- UNROM/UOROM (2): switchable 16KB bank at 0x8000, last bank fixed at 0xC000
+- UN1ROM (94): like UNROM, but the bank number is in bits 2..4 of the written value
+- UNROM + 74HC08 (180): first bank fixed at 0x8000, switchable 16KB bank at 0xC000
+ */
+pub struct UxROM {
+    pub pkg_rom: Vec<u8>,
+    pub pkg_rom_size: usize,
+    pub chr_rom: Vec<u8>,
+    pub chr_rom_size: usize,
+    pub namespace_mirroring: CartridgeMirroring,
+    pub mapper: u16,
+    pub bus_conflicts: bool,
+
+    pub pkg_bank: usize,
+
+    pub header: CartridgeHeader,
+}
+
+impl UxROM {
+    fn get_pkg_banks(&self) -> (usize, usize) {
+        let last_bank = (self.pkg_rom_size / 0x4000) - 1;
+        match self.mapper {
+            180 => (0, self.pkg_bank),
+            _ => (self.pkg_bank, last_bank),
+        }
+    }
+}
+
+impl Cartridge for UxROM {
+    fn read_pkg_byte(&mut self, addr: u16) -> u8 {
+        let (lower_bank, upper_bank) = self.get_pkg_banks();
+        let bank = if addr < 0x4000 {
+            lower_bank
+        } else {
+            upper_bank
+        };
+        let rom_addr = bank * 0x4000 + (addr as usize & 0x3FFF);
+        self.pkg_rom[rom_addr]
+    }
+
+    fn write_pkg_byte(&mut self, addr: u16, value: u8) {
+        //With bus conflicts, both the CPU and the ROM drive the data bus
+        let value = if self.bus_conflicts {
+            value & self.read_pkg_byte(addr)
+        } else {
+            value
+        };
+
+        let bank = match self.mapper {
+            94 => (value >> 2) & 0x7,
+            180 => value & 0x7,
+            _ => value,
+        };
+        self.pkg_bank = bank as usize % (self.pkg_rom_size / 0x4000);
+    }
+
+    fn read_chr_byte(&mut self, addr: u16) -> u8 {
+        self.chr_rom[addr as usize]
+    }
+
+    fn write_chr_byte(&mut self, addr: u16, value: u8) {
+        //Only CHR_RAM can be written
+        if self.chr_rom_size == 0 {
+            self.chr_rom[addr as usize] = value;
+        }
+    }
+
+    fn read_ram_byte(&mut self, _addr: u16) -> u8 {
+        0
+    }
+
+    fn write_ram_byte(&mut self, _addr: u16, _value: u8) {}
+
+    fn get_namespace_mirrored_address(&mut self, addr: u16) -> u16 {
+        self.namespace_mirroring.get_mirrored_address(addr)
+    }
+
+    fn get_header(&self) -> &CartridgeHeader {
+        &self.header
+    }
+}
+
+pub fn create_uxrom_from_rom(
+    header: CartridgeHeader,
+    rom: &Vec<u8>,
+) -> Result<Box<impl Cartridge>, RomError> {
+    let (pkg_rom, chr_rom) = read_ines_roms(&header, rom)?;
+    let pkg_rom_size = pkg_rom.len();
+    let chr_rom_size = chr_rom.len();
+
+    if pkg_rom_size % 0x4000 != 0 {
+        return Err(RomError::BadPkgRomSize(pkg_rom_size));
+    }
+
+    //UxROM boards normally use 8KB of CHR_RAM
+    let chr_rom = create_chr_memory(&header, chr_rom);
+
+    Ok(Box::new(UxROM {
+        pkg_rom,
+        pkg_rom_size,
+        chr_rom,
+        chr_rom_size,
+        namespace_mirroring: header.mirroring,
+        mapper: header.mapper,
+        //NES 2.0 submapper 2 marks boards with AND-type bus conflicts
+        bus_conflicts: header.mapper == 2 && header.submapper == 2,
+        pkg_bank: 0,
+        header,
+    }))
+}
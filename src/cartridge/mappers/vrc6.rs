@@ -0,0 +1,218 @@
+use crate::cartridge::header::CartridgeHeader;
+use crate::cartridge::mappers::vrc6_audio::Vrc6Audio;
+use crate::cartridge::mappers::vrc_irq::VrcIrq;
+use crate::cartridge::{
+    create_chr_memory, read_ines_roms, Cartridge, CartridgeMirroring, ExpansionAudio, RomError,
+};
+
+/*
+Konami VRC6 (Akumajou Densetsu, Madara, Esper Dream 2):
+- 16KB switchable bank at 0x8000, 8KB switchable bank at 0xC000, last 8KB bank fixed at 0xE000
+- eight 1KB CHR banks (or 2KB banks, depending on the PPU banking mode)
+- 8KB of PRG_RAM, CPU cycle based IRQ counter, 2 pulse channels and a sawtooth channel
+VRC6a (24) and VRC6b (26) are the same chip, with A0 and A1 swapped on the board
+ */
+pub struct VRC6 {
+    pub pkg_rom: Vec<u8>,
+    pub pkg_rom_size: usize,
+    pub chr_rom: Vec<u8>,
+    pub chr_rom_size: usize,
+    pub ram: Vec<u8>,
+    pub swapped_lines: bool,
+
+    pub pkg_bank_16k: usize,
+    pub pkg_bank_8k: usize,
+    pub chr_banks: [u8; 8],
+    pub ppu_banking: u8,
+    pub namespace_mirroring: CartridgeMirroring,
+
+    pub irq: VrcIrq,
+    pub audio: Vrc6Audio,
+
+    pub header: CartridgeHeader,
+}
+
+impl VRC6 {
+    //Returns the register address as seen by the chip (mapper 24 order)
+    fn get_register_address(&self, addr: u16) -> u16 {
+        let addr = 0x8000 | addr;
+        if self.swapped_lines {
+            (addr & 0xFFFC) | ((addr & 0x1) << 1) | ((addr & 0x2) >> 1)
+        } else {
+            addr
+        }
+    }
+
+    //Returns the 1KB bank for a pattern table address
+    fn get_chr_bank(&self, addr: u16) -> usize {
+        let slot = (addr as usize >> 10) & 0x7;
+        let a10 = ((addr >> 10) & 0x1) as u8;
+        //In 2KB banks, the lowest bit comes from the PPU address or from the bank register
+        let get_2k_bank = |register: u8| {
+            if self.ppu_banking & 0x20 != 0 {
+                (register & 0xFE) | a10
+            } else {
+                register
+            }
+        };
+
+        let bank = match self.ppu_banking & 0x3 {
+            0 => self.chr_banks[slot],
+            1 => get_2k_bank(self.chr_banks[slot >> 1]),
+            //0x0000 - 0x0FFF in 1KB banks, 0x1000 - 0x1FFF in 2KB banks
+            _ => {
+                if slot < 4 {
+                    self.chr_banks[slot]
+                } else {
+                    get_2k_bank(self.chr_banks[4 + ((slot - 4) >> 1)])
+                }
+            }
+        };
+        bank as usize
+    }
+
+    fn get_chr_address(&self, addr: u16) -> usize {
+        (self.get_chr_bank(addr) * 0x400 + (addr as usize & 0x3FF)) % self.chr_rom.len()
+    }
+
+    fn is_ram_enabled(&self) -> bool {
+        (self.ppu_banking & 0x80) != 0
+    }
+}
+
+impl Cartridge for VRC6 {
+    fn read_pkg_byte(&mut self, addr: u16) -> u8 {
+        let rom_addr = match addr {
+            0x0000..=0x3FFF => {
+                let bank = self.pkg_bank_16k % (self.pkg_rom_size / 0x4000);
+                bank * 0x4000 + (addr as usize & 0x3FFF)
+            }
+            0x4000..=0x5FFF => {
+                let bank = self.pkg_bank_8k % (self.pkg_rom_size / 0x2000);
+                bank * 0x2000 + (addr as usize & 0x1FFF)
+            }
+            _ => self.pkg_rom_size - 0x2000 + (addr as usize & 0x1FFF),
+        };
+        self.pkg_rom[rom_addr]
+    }
+
+    fn write_pkg_byte(&mut self, addr: u16, value: u8) {
+        let addr = self.get_register_address(addr);
+        match addr & 0xF003 {
+            0x8000..=0x8003 => self.pkg_bank_16k = (value & 0x0F) as usize,
+            0x9000..=0xB002 => self.audio.write_register(addr, value),
+            /*
+            PPU banking mode:
+            7  bit  0
+            ---- ----
+            Rxxx MMPP
+            |    ||||
+            |    ||++- CHR banking mode (0: 1KB banks, 1: 2KB banks, 2-3: mixed)
+            |    ++--- Mirroring (0: vertical, 1: horizontal, 2: one screen lower, 3: one screen upper)
+            +--------- PRG_RAM enable
+            Bit 4 (nametables from CHR_ROM) isn't used by any game, and is ignored
+             */
+            0xB003 => {
+                self.ppu_banking = value;
+                self.namespace_mirroring = match (value >> 2) & 0x3 {
+                    0 => CartridgeMirroring::VERTICAL,
+                    1 => CartridgeMirroring::HORIZONTAL,
+                    2 => CartridgeMirroring::SingleScreenLower,
+                    _ => CartridgeMirroring::SingleScreenUpper,
+                };
+            }
+            0xC000..=0xC003 => self.pkg_bank_8k = (value & 0x1F) as usize,
+            0xD000..=0xD003 => self.chr_banks[(addr & 0x3) as usize] = value,
+            0xE000..=0xE003 => self.chr_banks[4 + (addr & 0x3) as usize] = value,
+            0xF000 => self.irq.write_latch(value),
+            0xF001 => self.irq.write_control(value),
+            0xF002 => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+
+    fn read_chr_byte(&mut self, addr: u16) -> u8 {
+        self.chr_rom[self.get_chr_address(addr)]
+    }
+
+    fn write_chr_byte(&mut self, addr: u16, value: u8) {
+        //Only CHR_RAM can be written
+        if self.chr_rom_size == 0 {
+            let chr_addr = self.get_chr_address(addr);
+            self.chr_rom[chr_addr] = value;
+        }
+    }
+
+    fn read_ram_byte(&mut self, addr: u16) -> u8 {
+        if !self.is_ram_enabled() {
+            return 0;
+        }
+        self.ram[addr as usize]
+    }
+
+    fn write_ram_byte(&mut self, addr: u16, value: u8) {
+        if self.is_ram_enabled() {
+            self.ram[addr as usize] = value;
+        }
+    }
+
+    fn get_namespace_mirrored_address(&mut self, addr: u16) -> u16 {
+        self.namespace_mirroring.get_mirrored_address(addr)
+    }
+
+    fn get_header(&self) -> &CartridgeHeader {
+        &self.header
+    }
+
+    fn clock_cpu(&mut self, cpu_cycles: u32) {
+        self.irq.clock(cpu_cycles);
+    }
+
+    fn is_irq_requested(&self) -> bool {
+        self.irq.requested
+    }
+
+    fn get_battery_ram(&mut self) -> Option<&mut [u8]> {
+        if self.header.has_battery {
+            Some(&mut self.ram)
+        } else {
+            None
+        }
+    }
+
+    fn get_expansion_audio(&mut self) -> Option<&mut dyn ExpansionAudio> {
+        Some(&mut self.audio)
+    }
+}
+
+pub fn create_vrc6_from_rom(
+    header: CartridgeHeader,
+    rom: &Vec<u8>,
+) -> Result<Box<impl Cartridge>, RomError> {
+    let (pkg_rom, chr_rom) = read_ines_roms(&header, rom)?;
+    let pkg_rom_size = pkg_rom.len();
+    let chr_rom_size = chr_rom.len();
+
+    if pkg_rom_size < 0x4000 || pkg_rom_size % 0x4000 != 0 {
+        return Err(RomError::BadPkgRomSize(pkg_rom_size));
+    }
+
+    let chr_rom = create_chr_memory(&header, chr_rom);
+
+    Ok(Box::new(VRC6 {
+        pkg_rom,
+        pkg_rom_size,
+        chr_rom,
+        chr_rom_size,
+        ram: vec![0; 0x2000],
+        swapped_lines: header.mapper == 26,
+        pkg_bank_16k: 0,
+        pkg_bank_8k: 0,
+        chr_banks: [0; 8],
+        ppu_banking: 0,
+        namespace_mirroring: header.mirroring,
+        irq: VrcIrq::new(),
+        audio: Vrc6Audio::new(),
+        header,
+    }))
+}
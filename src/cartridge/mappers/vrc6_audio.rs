@@ -0,0 +1,214 @@
+use crate::cartridge::ExpansionAudio;
+
+//Highest output: two pulses at volume 15 and the sawtooth at 31
+const VRC6_MAX_OUTPUT: f32 = 61.0;
+//A VRC6 pulse at volume 15 is about as loud as a 2A03 pulse at volume 15
+const VRC6_RELATIVE_LEVEL: f32 = 0.6;
+
+struct Vrc6Pulse {
+    volume: u8,
+    duty: u8,
+    //Ignore the duty, the output is always the volume
+    constant: bool,
+    period: u16,
+    enabled: bool,
+    divider: u16,
+    //Counts down from 15, the output is high while step <= duty
+    step: u8,
+}
+
+impl Vrc6Pulse {
+    fn new() -> Vrc6Pulse {
+        Vrc6Pulse {
+            volume: 0,
+            duty: 0,
+            constant: false,
+            period: 0,
+            enabled: false,
+            divider: 0,
+            step: 15,
+        }
+    }
+
+    /*
+    Register 0: MDDD VVVV, mode (constant output), duty, volume
+    Register 1: FFFF FFFF, period low 8 bits
+    Register 2: Exxx FFFF, enable, period high 4 bits
+     */
+    fn write_register(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.constant = (value & 0x80) != 0;
+                self.duty = (value >> 4) & 0x7;
+                self.volume = value & 0xF;
+            }
+            1 => self.period = (self.period & 0xF00) | u16::from(value),
+            _ => {
+                self.period = (self.period & 0x0FF) | (u16::from(value & 0xF) << 8);
+                self.enabled = (value & 0x80) != 0;
+                if !self.enabled {
+                    self.step = 15;
+                }
+            }
+        }
+    }
+
+    fn clock_timer(&mut self, period_shift: u8) {
+        if !self.enabled {
+            return;
+        }
+        if self.divider == 0 {
+            self.divider = self.period >> period_shift;
+            self.step = if self.step == 0 { 15 } else { self.step - 1 };
+        } else {
+            self.divider -= 1;
+        }
+    }
+
+    fn output(&self) -> u8 {
+        if self.enabled && (self.constant || self.step <= self.duty) {
+            self.volume
+        } else {
+            0
+        }
+    }
+}
+
+struct Vrc6Sawtooth {
+    rate: u8,
+    period: u16,
+    enabled: bool,
+    divider: u16,
+    //The accumulator is increased every other step, and cleared after 14 steps
+    step: u8,
+    accumulator: u8,
+}
+
+impl Vrc6Sawtooth {
+    fn new() -> Vrc6Sawtooth {
+        Vrc6Sawtooth {
+            rate: 0,
+            period: 0,
+            enabled: false,
+            divider: 0,
+            step: 0,
+            accumulator: 0,
+        }
+    }
+
+    /*
+    Register 0: xxAA AAAA, accumulator rate
+    Register 1: FFFF FFFF, period low 8 bits
+    Register 2: Exxx FFFF, enable, period high 4 bits
+     */
+    fn write_register(&mut self, register: u16, value: u8) {
+        match register {
+            0 => self.rate = value & 0x3F,
+            1 => self.period = (self.period & 0xF00) | u16::from(value),
+            _ => {
+                self.period = (self.period & 0x0FF) | (u16::from(value & 0xF) << 8);
+                self.enabled = (value & 0x80) != 0;
+                if !self.enabled {
+                    self.step = 0;
+                    self.accumulator = 0;
+                }
+            }
+        }
+    }
+
+    fn clock_timer(&mut self, period_shift: u8) {
+        if !self.enabled {
+            return;
+        }
+        if self.divider == 0 {
+            self.divider = self.period >> period_shift;
+            self.step += 1;
+            if self.step == 14 {
+                self.step = 0;
+                self.accumulator = 0;
+            } else if self.step & 0x1 == 0 {
+                self.accumulator = self.accumulator.wrapping_add(self.rate);
+            }
+        } else {
+            self.divider -= 1;
+        }
+    }
+
+    //Only the 5 high bits of the accumulator reach the DAC
+    fn output(&self) -> u8 {
+        self.accumulator >> 3
+    }
+}
+
+//Two pulse channels and a sawtooth channel, clocked by the CPU clock
+pub struct Vrc6Audio {
+    pulse_1: Vrc6Pulse,
+    pulse_2: Vrc6Pulse,
+    sawtooth: Vrc6Sawtooth,
+    halted: bool,
+    //The frequency control register can speed up the timers by 16 or 256
+    period_shift: u8,
+}
+
+impl Vrc6Audio {
+    pub fn new() -> Vrc6Audio {
+        Vrc6Audio {
+            pulse_1: Vrc6Pulse::new(),
+            pulse_2: Vrc6Pulse::new(),
+            sawtooth: Vrc6Sawtooth::new(),
+            halted: false,
+            period_shift: 0,
+        }
+    }
+
+    //addr is the CPU address, with the address lines in the mapper 24 order
+    pub fn write_register(&mut self, addr: u16, value: u8) {
+        let register = addr & 0x3;
+        match addr & 0xF003 {
+            0x9000..=0x9002 => self.pulse_1.write_register(register, value),
+            /*
+            Frequency control:
+            7  bit  0
+            ---- ----
+            xxxx xABH
+                  |||
+                  ||+- Halt all the oscillators
+                  |+-- Timers are 16 times faster
+                  +--- Timers are 256 times faster (over B)
+             */
+            0x9003 => {
+                self.halted = (value & 0x1) != 0;
+                self.period_shift = if value & 0x4 != 0 {
+                    8
+                } else if value & 0x2 != 0 {
+                    4
+                } else {
+                    0
+                };
+            }
+            0xA000..=0xA002 => self.pulse_2.write_register(register, value),
+            0xB000..=0xB002 => self.sawtooth.write_register(register, value),
+            _ => {}
+        }
+    }
+}
+
+impl ExpansionAudio for Vrc6Audio {
+    fn clock(&mut self) {
+        if self.halted {
+            return;
+        }
+        self.pulse_1.clock_timer(self.period_shift);
+        self.pulse_2.clock_timer(self.period_shift);
+        self.sawtooth.clock_timer(self.period_shift);
+    }
+
+    fn get_sample(&self) -> f32 {
+        let output = self.pulse_1.output() + self.pulse_2.output() + self.sawtooth.output();
+        f32::from(output) / VRC6_MAX_OUTPUT
+    }
+
+    fn get_relative_level(&self) -> f32 {
+        VRC6_RELATIVE_LEVEL
+    }
+}
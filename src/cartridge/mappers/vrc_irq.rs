@@ -0,0 +1,88 @@
+//The prescaler divides the CPU clock by 113.667 (341 / 3) to clock the counter once per scanline
+const PRESCALER_PERIOD: i16 = 341;
+const PRESCALER_STEP: i16 = 3;
+
+/*
+IRQ counter of the Konami VRC4, VRC6 and VRC7: it counts CPU cycles, not PPU addresses.
+The 8 bits counter is clocked once per scanline (using the prescaler) or once per CPU cycle,
+and raises the IRQ when it overflows, reloading the latch
+ */
+pub struct VrcIrq {
+    pub latch: u8,
+    pub counter: u8,
+    pub prescaler: i16,
+    pub enabled: bool,
+    pub enabled_after_ack: bool,
+    pub cycle_mode: bool,
+    pub requested: bool,
+}
+
+impl VrcIrq {
+    pub fn new() -> VrcIrq {
+        VrcIrq {
+            latch: 0,
+            counter: 0,
+            prescaler: PRESCALER_PERIOD,
+            enabled: false,
+            enabled_after_ack: false,
+            cycle_mode: false,
+            requested: false,
+        }
+    }
+
+    pub fn write_latch(&mut self, value: u8) {
+        self.latch = value;
+    }
+
+    /*
+    IRQ control:
+    7  bit  0
+    ---- ----
+    xxxx xMEA
+          |||
+          ||+- Enable after acknowledgement
+          |+-- Enable
+          +--- Mode (0: scanline, 1: CPU cycle)
+     */
+    pub fn write_control(&mut self, value: u8) {
+        self.enabled_after_ack = (value & 0x1) != 0;
+        self.enabled = (value & 0x2) != 0;
+        self.cycle_mode = (value & 0x4) != 0;
+        self.requested = false;
+        if self.enabled {
+            self.counter = self.latch;
+            self.prescaler = PRESCALER_PERIOD;
+        }
+    }
+
+    pub fn acknowledge(&mut self) {
+        self.requested = false;
+        self.enabled = self.enabled_after_ack;
+    }
+
+    pub fn clock(&mut self, cpu_cycles: u32) {
+        if !self.enabled {
+            return;
+        }
+        for _ in 0..cpu_cycles {
+            if self.cycle_mode {
+                self.clock_counter();
+            } else {
+                self.prescaler -= PRESCALER_STEP;
+                if self.prescaler <= 0 {
+                    self.prescaler += PRESCALER_PERIOD;
+                    self.clock_counter();
+                }
+            }
+        }
+    }
+
+    fn clock_counter(&mut self) {
+        if self.counter == 0xFF {
+            self.counter = self.latch;
+            self.requested = true;
+        } else {
+            self.counter += 1;
+        }
+    }
+}
@@ -1,7 +1,20 @@
+pub mod header;
 mod mappers;
+pub mod nsf;
 
+use crate::cartridge::header::{
+    parse_ines_header, CartridgeHeader, INES_HEADER_SIZE, INES_TRAINER_SIZE,
+};
+use crate::cartridge::mappers::axrom::create_axrom_from_rom;
+use crate::cartridge::mappers::cnrom::create_cnrom_from_rom;
 use crate::cartridge::mappers::mmc1::create_mmc1_from_rom;
+use crate::cartridge::mappers::mmc3::create_mmc3_from_rom;
 use crate::cartridge::mappers::nrom::create_nrom_from_rom;
+use crate::cartridge::mappers::nsf::create_nsf_cartridge;
+use crate::cartridge::mappers::uxrom::create_uxrom_from_rom;
+use crate::cartridge::mappers::vrc6::create_vrc6_from_rom;
+use crate::cartridge::nsf::{parse_nsf, NsfInfo};
+use std::fmt;
 
 pub trait Cartridge {
     fn read_pkg_byte(&mut self, addr: u16) -> u8;
@@ -14,9 +27,51 @@ pub trait Cartridge {
     fn write_ram_byte(&mut self, addr: u16, value: u8);
 
     fn get_namespace_mirrored_address(&mut self, addr: u16) -> u16;
+
+    fn get_header(&self) -> &CartridgeHeader;
+
+    //Expansion area (0x4020 - 0x5FFF), addr is the CPU address
+    fn read_expansion_byte(&mut self, _addr: u16) -> u8 {
+        0
+    }
+    fn write_expansion_byte(&mut self, _addr: u16, _value: u8) {}
+
+    //Called with every address the PPU puts on its bus, for mappers that watch it (like MMC3 with A12)
+    fn notify_ppu_address(&mut self, _addr: u16) {}
+
+    //Called after every CPU instruction, for mappers counting CPU cycles (like the VRC IRQ)
+    fn clock_cpu(&mut self, _cpu_cycles: u32) {}
+
+    //True while the cartridge is asserting the CPU IRQ line
+    fn is_irq_requested(&self) -> bool {
+        false
+    }
+
+    //Battery backed memory, that must survive between sessions. None if the cartridge has no battery
+    fn get_battery_ram(&mut self) -> Option<&mut [u8]> {
+        None
+    }
+
+    //Sound chip of the cartridge. None if the cartridge has no expansion audio
+    fn get_expansion_audio(&mut self) -> Option<&mut dyn ExpansionAudio> {
+        None
+    }
 }
 
-#[derive(Copy, Clone)]
+//Sound chip on the cartridge (VRC6, VRC7, Namco 163, Sunsoft 5B, MMC5, FDS...), mixed with the 2A03 by the APU
+pub trait ExpansionAudio {
+    //Called once per CPU cycle
+    fn clock(&mut self);
+
+    //Current output of the chip, between 0.0 and 1.0
+    fn get_sample(&self) -> f32;
+
+    //Output of the chip at full scale, relative to the full scale of the 2A03 mixer.
+    //Each chip is louder or quieter than the console
+    fn get_relative_level(&self) -> f32;
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum CartridgeMirroring {
     HORIZONTAL,
     VERTICAL,
@@ -24,24 +79,144 @@ pub enum CartridgeMirroring {
     SingleScreenUpper,
 }
 
-pub fn from_ines(rom: &Vec<u8>) -> Box<dyn Cartridge> {
-    //TODO check header
-    let pkg_rom_size = rom[4] as usize * 16384;
-    let chr_rom_size = rom[5] as usize * 8192;
-    let flag6 = rom[6];
-    let flag7 = rom[7];
+impl CartridgeMirroring {
+    //Maps a nametable address (0x000 - 0xFFF) into the 2KB of PPU VRAM
+    pub fn get_mirrored_address(&self, addr: u16) -> u16 {
+        let base_addr = addr & 0x3FF;
+
+        return match self {
+            CartridgeMirroring::HORIZONTAL => ((addr & 0x800) >> 1) | base_addr,
+            CartridgeMirroring::VERTICAL => (addr & 0x400) | base_addr,
+            CartridgeMirroring::SingleScreenLower => base_addr,
+            CartridgeMirroring::SingleScreenUpper => 0x400 | base_addr,
+        };
+    }
+}
+
+#[derive(Debug)]
+pub enum RomError {
+    MissingMagic,
+    TruncatedHeader,
+    TruncatedPkgRom { expected: usize, found: usize },
+    TruncatedChrRom { expected: usize, found: usize },
+    BadPkgRomSize(usize),
+    UnsupportedMapper(u16),
+    TruncatedNsfHeader,
+    TruncatedNsfChunk,
+    MissingNsfChunk(&'static str),
+    UnsupportedNsfChunk(String),
+    BadNsfLoadAddress(u16),
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomError::MissingMagic => write!(f, "not an iNES file (missing NES\\x1A magic)"),
+            RomError::TruncatedHeader => write!(f, "file is too short for an iNES header"),
+            RomError::TruncatedPkgRom { expected, found } => write!(
+                f,
+                "PRG ROM is truncated (expected {} bytes, found {})",
+                expected, found
+            ),
+            RomError::TruncatedChrRom { expected, found } => write!(
+                f,
+                "CHR ROM is truncated (expected {} bytes, found {})",
+                expected, found
+            ),
+            RomError::BadPkgRomSize(size) => write!(f, "bad PRG ROM size ({} bytes)", size),
+            RomError::UnsupportedMapper(mapper) => write!(f, "unsupported mapper {}", mapper),
+            RomError::TruncatedNsfHeader => write!(f, "file is too short for a NSF header"),
+            RomError::TruncatedNsfChunk => write!(f, "NSFe chunk is truncated"),
+            RomError::MissingNsfChunk(id) => write!(f, "missing NSFe {} chunk", id),
+            RomError::UnsupportedNsfChunk(id) => {
+                write!(f, "unsupported required NSFe chunk {}", id)
+            }
+            RomError::BadNsfLoadAddress(addr) => {
+                write!(
+                    f,
+                    "bad NSF load address {:04X} without bank switching",
+                    addr
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for RomError {}
+
+//Returns PKG_ROM and CHR_ROM. An empty CHR_ROM means that the cartridge uses CHR_RAM
+fn read_ines_roms(header: &CartridgeHeader, rom: &Vec<u8>) -> Result<(Vec<u8>, Vec<u8>), RomError> {
+    let pkg_rom_size = header.pkg_rom_size;
+    let chr_rom_size = header.chr_rom_size;
 
-    let mapper = (flag7 & 0xF0) | ((flag6 & 0xF0) >> 4);
+    //The trainer (if any) sits between the header and PKG_ROM
+    let pkg_rom_start_index = INES_HEADER_SIZE
+        + if header.has_trainer {
+            INES_TRAINER_SIZE
+        } else {
+            0
+        };
+    let available = rom.len().saturating_sub(pkg_rom_start_index);
+
+    if available < pkg_rom_size {
+        return Err(RomError::TruncatedPkgRom {
+            expected: pkg_rom_size,
+            found: available,
+        });
+    }
+    if available - pkg_rom_size < chr_rom_size {
+        return Err(RomError::TruncatedChrRom {
+            expected: chr_rom_size,
+            found: available - pkg_rom_size,
+        });
+    }
+
+    let chr_rom_start_index = pkg_rom_start_index + pkg_rom_size;
+    let pkg_rom = rom[pkg_rom_start_index..chr_rom_start_index].to_vec();
+    let chr_rom = rom[chr_rom_start_index..chr_rom_start_index + chr_rom_size].to_vec();
+    Ok((pkg_rom, chr_rom))
+}
+
+//If the cartridge has no CHR_ROM, the PPU pattern tables are in CHR_RAM
+fn create_chr_memory(header: &CartridgeHeader, chr_rom: Vec<u8>) -> Vec<u8> {
+    if chr_rom.is_empty() {
+        let chr_ram_size = header.chr_ram_size + header.chr_nvram_size;
+        vec![0; chr_ram_size.max(0x2000)]
+    } else {
+        chr_rom
+    }
+}
+
+pub fn from_ines(rom: &Vec<u8>) -> Result<Box<dyn Cartridge>, RomError> {
+    let header = parse_ines_header(rom)?;
+    let mapper = header.mapper;
 
     println!("file size {}", rom.len());
     println!(
-        "pkg_rom_size {}, chr_rom_size {}, mapper {}",
-        pkg_rom_size, chr_rom_size, mapper
+        "pkg_rom_size {}, chr_rom_size {}, mapper {}, submapper {}",
+        header.pkg_rom_size, header.chr_rom_size, mapper, header.submapper
     );
 
     return match mapper {
-        0 => create_nrom_from_rom(rom),
-        1 => create_mmc1_from_rom(rom),
-        _ => panic!("Unsupported mapper"),
+        0 => Ok(create_nrom_from_rom(header, rom)?),
+        1 => Ok(create_mmc1_from_rom(header, rom)?),
+        2 | 94 | 180 => Ok(create_uxrom_from_rom(header, rom)?),
+        3 => Ok(create_cnrom_from_rom(header, rom)?),
+        4 | 118 | 119 => Ok(create_mmc3_from_rom(header, rom)?),
+        7 => Ok(create_axrom_from_rom(header, rom)?),
+        24 | 26 => Ok(create_vrc6_from_rom(header, rom)?),
+        _ => Err(RomError::UnsupportedMapper(mapper)),
     };
 }
+
+//NSF and NSFe music files
+pub fn from_nsf(rom: &[u8]) -> Result<(Box<dyn Cartridge>, NsfInfo), RomError> {
+    let (info, data) = parse_nsf(rom)?;
+    println!(
+        "NSF load {:04X}, init {:04X}, play {:04X}, {} songs",
+        info.load_address, info.init_address, info.play_address, info.song_count
+    );
+
+    let cartridge = create_nsf_cartridge(&info, &data);
+    Ok((cartridge, info))
+}
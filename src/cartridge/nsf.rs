@@ -0,0 +1,303 @@
+use crate::cartridge::RomError;
+
+pub const NSF_MAGIC: [u8; 5] = [0x4E, 0x45, 0x53, 0x4D, 0x1A];
+pub const NSFE_MAGIC: [u8; 4] = [0x4E, 0x53, 0x46, 0x45];
+const NSF_HEADER_SIZE: usize = 0x80;
+
+//Expansion sound chips, byte 0x7B of the NSF header
+pub const NSF_CHIP_VRC6: u8 = 0b000001;
+pub const NSF_CHIP_VRC7: u8 = 0b000010;
+pub const NSF_CHIP_FDS: u8 = 0b000100;
+pub const NSF_CHIP_MMC5: u8 = 0b001000;
+pub const NSF_CHIP_NAMCO_163: u8 = 0b010000;
+pub const NSF_CHIP_SUNSOFT_5B: u8 = 0b100000;
+//Chips emulated by the NSF cartridge
+const NSF_SUPPORTED_CHIPS: u8 = NSF_CHIP_VRC6;
+
+//Everything needed to play a NSF (or NSFe) file, the program itself is in the cartridge
+#[derive(Clone, Debug)]
+pub struct NsfInfo {
+    pub load_address: u16,
+    pub init_address: u16,
+    pub play_address: u16,
+    pub song_count: u8,
+    //0-based
+    pub starting_song: u8,
+    pub title: String,
+    pub artist: String,
+    pub copyright: String,
+    //Microseconds between two calls of PLAY
+    pub ntsc_speed: u16,
+    pub pal_speed: u16,
+    pub is_pal: bool,
+    //Initial 4K banks of 0x8000 - 0xFFFF, None if the file doesn't use bank switching
+    pub bankswitch: Option<[u8; 8]>,
+    pub expansion_chips: u8,
+    //NSFe only, empty for a NSF file
+    pub track_titles: Vec<String>,
+    //NSFe only, in milliseconds (None if unknown)
+    pub track_lengths: Vec<Option<u32>>,
+    //Order in which the tracks are played, all of them if the file has no playlist
+    pub playlist: Vec<u8>,
+}
+
+impl NsfInfo {
+    pub fn get_play_speed(&self) -> u16 {
+        if self.is_pal {
+            self.pal_speed
+        } else {
+            self.ntsc_speed
+        }
+    }
+
+    pub fn get_track_title(&self, track: u8) -> Option<&str> {
+        self.track_titles
+            .get(track as usize)
+            .map(|title| title.as_str())
+            .filter(|title| !title.is_empty())
+    }
+
+    //Expansion chips used by the file that can't be played
+    pub fn get_unsupported_chip_names(&self) -> Vec<&'static str> {
+        let chips = [
+            (NSF_CHIP_VRC6, "VRC6"),
+            (NSF_CHIP_VRC7, "VRC7"),
+            (NSF_CHIP_FDS, "FDS"),
+            (NSF_CHIP_MMC5, "MMC5"),
+            (NSF_CHIP_NAMCO_163, "Namco 163"),
+            (NSF_CHIP_SUNSOFT_5B, "Sunsoft 5B"),
+        ];
+        chips
+            .iter()
+            .filter(|(chip, _)| self.expansion_chips & !NSF_SUPPORTED_CHIPS & chip != 0)
+            .map(|(_, name)| *name)
+            .collect()
+    }
+}
+
+pub fn is_nsf(rom: &[u8]) -> bool {
+    rom.starts_with(&NSF_MAGIC) || rom.starts_with(&NSFE_MAGIC)
+}
+
+fn read_word(data: &[u8], offset: usize) -> u16 {
+    u16::from(data[offset]) | (u16::from(data[offset + 1]) << 8)
+}
+
+//Text fields are zero terminated (or fill the whole field)
+fn read_string(data: &[u8]) -> String {
+    let end = data.iter().position(|&c| c == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).into_owned()
+}
+
+fn read_string_list(data: &[u8]) -> Vec<String> {
+    let data = data.strip_suffix(&[0]).unwrap_or(data);
+    data.split(|&c| c == 0).map(read_string).collect()
+}
+
+fn get_bankswitch(banks: [u8; 8]) -> Option<[u8; 8]> {
+    if banks.iter().any(|&bank| bank != 0) {
+        Some(banks)
+    } else {
+        None
+    }
+}
+
+/*
+Byte 0-4: "NESM" followed by MS-DOS end-of-file
+Byte 5: version
+Byte 6: total songs
+Byte 7: starting song (1-based)
+Byte 8-9: load address
+Byte A-B: init address
+Byte C-D: play address
+Byte E-2D: song name
+Byte 2E-4D: artist
+Byte 4E-6D: copyright
+Byte 6E-6F: NTSC speed (microseconds)
+Byte 70-77: bankswitch init values
+Byte 78-79: PAL speed (microseconds)
+Byte 7A: xxxx xxDP, D: dual PAL/NTSC, P: PAL
+Byte 7B: expansion sound chips
+Byte 7C: NSF2 flags
+Byte 7D-7F: NSF2 program data length (0 if the data goes to the end of the file)
+Returns the info and the program data
+ */
+pub fn parse_nsf(rom: &[u8]) -> Result<(NsfInfo, Vec<u8>), RomError> {
+    if rom.starts_with(&NSFE_MAGIC) {
+        return parse_nsfe(rom);
+    }
+    if !rom.starts_with(&NSF_MAGIC) {
+        return Err(RomError::MissingMagic);
+    }
+    if rom.len() < NSF_HEADER_SIZE {
+        return Err(RomError::TruncatedNsfHeader);
+    }
+
+    let data_length =
+        usize::from(rom[0x7D]) | (usize::from(rom[0x7E]) << 8) | (usize::from(rom[0x7F]) << 16);
+    let data_end = if rom[5] >= 2 && data_length != 0 {
+        (NSF_HEADER_SIZE + data_length).min(rom.len())
+    } else {
+        rom.len()
+    };
+
+    let mut banks = [0; 8];
+    banks.copy_from_slice(&rom[0x70..0x78]);
+    let song_count = rom[6].max(1);
+    let info = NsfInfo {
+        load_address: read_word(rom, 0x08),
+        init_address: read_word(rom, 0x0A),
+        play_address: read_word(rom, 0x0C),
+        song_count,
+        starting_song: rom[7].saturating_sub(1).min(song_count - 1),
+        title: read_string(&rom[0x0E..0x2E]),
+        artist: read_string(&rom[0x2E..0x4E]),
+        copyright: read_string(&rom[0x4E..0x6E]),
+        ntsc_speed: read_word(rom, 0x6E),
+        pal_speed: read_word(rom, 0x78),
+        //A dual region tune is played at the NTSC speed
+        is_pal: rom[0x7A] & 0x3 == 0x1,
+        bankswitch: get_bankswitch(banks),
+        expansion_chips: rom[0x7B],
+        track_titles: Vec::new(),
+        track_lengths: Vec::new(),
+        playlist: (0..song_count).collect(),
+    };
+    let data = rom[NSF_HEADER_SIZE..data_end].to_vec();
+    check_load_address(&info)?;
+    Ok((info, data))
+}
+
+/*
+NSFe: "NSFE" followed by chunks, each one is:
+Byte 0-3: length of the data
+Byte 4-7: id of the chunk (an id starting by an uppercase letter can't be skipped)
+Byte 8-: data
+INFO and DATA are required, NEND ends the file
+ */
+fn parse_nsfe(rom: &[u8]) -> Result<(NsfInfo, Vec<u8>), RomError> {
+    let mut info: Option<NsfInfo> = None;
+    let mut data: Option<Vec<u8>> = None;
+    let mut banks = [0; 8];
+    let mut rate: Option<&[u8]> = None;
+    let mut authors: Vec<String> = Vec::new();
+    let mut track_titles = Vec::new();
+    let mut track_lengths = Vec::new();
+    let mut playlist: Option<Vec<u8>> = None;
+
+    let mut offset = NSFE_MAGIC.len();
+    while offset < rom.len() {
+        if rom.len() - offset < 8 {
+            return Err(RomError::TruncatedNsfChunk);
+        }
+        let length = u32::from_le_bytes([
+            rom[offset],
+            rom[offset + 1],
+            rom[offset + 2],
+            rom[offset + 3],
+        ]) as usize;
+        let id = &rom[offset + 4..offset + 8];
+        offset += 8;
+        if rom.len() - offset < length {
+            return Err(RomError::TruncatedNsfChunk);
+        }
+        let chunk = &rom[offset..offset + length];
+        offset += length;
+
+        match id {
+            b"INFO" => {
+                if chunk.len() < 8 {
+                    return Err(RomError::TruncatedNsfChunk);
+                }
+                let song_count = chunk.get(8).copied().unwrap_or(1).max(1);
+                info = Some(NsfInfo {
+                    load_address: read_word(chunk, 0),
+                    init_address: read_word(chunk, 2),
+                    play_address: read_word(chunk, 4),
+                    song_count,
+                    starting_song: chunk.get(9).copied().unwrap_or(0).min(song_count - 1),
+                    title: String::new(),
+                    artist: String::new(),
+                    copyright: String::new(),
+                    //Default speeds, that can be changed by the RATE chunk
+                    ntsc_speed: 16639,
+                    pal_speed: 19997,
+                    is_pal: chunk[6] & 0x3 == 0x1,
+                    bankswitch: None,
+                    expansion_chips: chunk[7],
+                    track_titles: Vec::new(),
+                    track_lengths: Vec::new(),
+                    playlist: Vec::new(),
+                });
+            }
+            b"DATA" => data = Some(chunk.to_vec()),
+            b"BANK" => {
+                let len = chunk.len().min(8);
+                banks[..len].copy_from_slice(&chunk[..len]);
+            }
+            b"RATE" => rate = Some(chunk),
+            b"NEND" => break,
+            b"auth" => authors = read_string_list(chunk),
+            b"tlbl" => track_titles = read_string_list(chunk),
+            b"time" => {
+                track_lengths = chunk
+                    .chunks_exact(4)
+                    .map(|length| {
+                        let length =
+                            i32::from_le_bytes([length[0], length[1], length[2], length[3]]);
+                        u32::try_from(length).ok()
+                    })
+                    .collect()
+            }
+            b"plst" => playlist = Some(chunk.to_vec()),
+            _ if id[0].is_ascii_uppercase() => {
+                return Err(RomError::UnsupportedNsfChunk(
+                    String::from_utf8_lossy(id).into_owned(),
+                ));
+            }
+            //Optional chunk
+            _ => {}
+        }
+    }
+
+    let mut info = info.ok_or(RomError::MissingNsfChunk("INFO"))?;
+    let data = data.ok_or(RomError::MissingNsfChunk("DATA"))?;
+
+    if let Some(rate) = rate {
+        if rate.len() >= 2 {
+            info.ntsc_speed = read_word(rate, 0);
+        }
+        if rate.len() >= 4 {
+            info.pal_speed = read_word(rate, 2);
+        }
+    }
+    let mut authors = authors.into_iter();
+    info.title = authors.next().unwrap_or_default();
+    info.artist = authors.next().unwrap_or_default();
+    info.copyright = authors.next().unwrap_or_default();
+    info.bankswitch = get_bankswitch(banks);
+    info.track_titles = track_titles;
+    info.track_lengths = track_lengths;
+    let song_count = info.song_count;
+    info.playlist = match playlist {
+        Some(playlist) => playlist
+            .into_iter()
+            .filter(|&track| track < song_count)
+            .collect(),
+        None => Vec::new(),
+    };
+    if info.playlist.is_empty() {
+        info.playlist = (0..song_count).collect();
+    }
+
+    check_load_address(&info)?;
+    Ok((info, data))
+}
+
+//Without bank switching the data is loaded as is in 0x8000 - 0xFFFF
+fn check_load_address(info: &NsfInfo) -> Result<(), RomError> {
+    if info.bankswitch.is_none() && info.load_address < 0x8000 {
+        return Err(RomError::BadNsfLoadAddress(info.load_address));
+    }
+    Ok(())
+}
@@ -1,27 +1,42 @@
 extern crate core;
 
-use crate::cartridge::from_ines;
+use crate::audio::AudioOutput;
+use crate::battery::BatterySave;
+use crate::cartridge::nsf::{is_nsf, NsfInfo};
+use crate::cartridge::{from_ines, from_nsf};
 use crate::nes::NesControllerButton::START;
-use crate::nes::{Nes, NesControllerButton};
+use crate::nes::{ApuChannel, Nes, NesControllerButton};
+use crate::options::{Options, USAGE};
+use crate::pacing::{FrameTimer, PacingSource};
+use crate::wav::AudioRecorder;
 use crate::NesControllerButton::{A, B, DOWN, LEFT, RIGHT, SELECT, UP};
 use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+use sdl2::keyboard::{Keycode, Mod};
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
 use sdl2::render::WindowCanvas;
 use std::fs::File;
 use std::io::Read;
 use std::mem::size_of;
-use std::ops::Div;
-use std::thread::sleep;
-use std::time::{Duration, SystemTime};
-use std::{env, fs};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{env, fs, process};
 
+pub mod audio;
+pub mod battery;
 pub mod cartridge;
 pub mod memory;
+pub mod options;
+pub mod pacing;
+pub mod wav;
 
 pub mod nes;
 
+//Battery backed RAM is written on disk every 5 seconds (if changed) and on exit
+const SAVE_FLUSH_FRAMES: u32 = 300;
+//NTSC CPU clock
+const CPU_FREQUENCY: u32 = 1789773;
+
 fn draw_screen(canvas: &mut WindowCanvas, screen_pixels_rgba: &[u32; 256 * 240]) {
     let scaling = 3;
 
@@ -66,20 +81,257 @@ fn convert_keycode_to_nes(key: Option<Keycode>) -> Option<NesControllerButton> {
     }
 }
 
+//Runs the emulation until the start of the next VBlank, returns the CPU cycles
+fn run_frame(nes: &mut Nes) -> u32 {
+    //A NTSC frame is 29780.5 CPU cycles, so the NES runs at ~60.0988 frames per second
+    let frame = nes.get_frame_count();
+    let mut frame_cycles = 0;
+    while nes.get_frame_count() == frame {
+        let cpu_clock = nes.execute_instruction();
+        nes.execute_ppu(cpu_clock);
+        nes.execute_apu(cpu_clock);
+        frame_cycles += cpu_clock;
+    }
+    frame_cycles
+}
+
+fn report_cpu_jam(nes: &Nes, jam_reported: &mut bool) {
+    if let Some(jam) = nes.get_cpu_jam() {
+        if !*jam_reported {
+            eprintln!(
+                "CPU halted by JAM opcode {:02X} at {:04X}",
+                jam.opcode, jam.address
+            );
+            *jam_reported = true;
+        }
+    }
+}
+
+fn start_recording(path: &Path, options: &Options, nes: &mut Nes) -> Option<AudioRecorder> {
+    return match AudioRecorder::start(path, CPU_FREQUENCY, options.record_channels, nes) {
+        Ok(recorder) => {
+            println!("Recording audio in {}", path.display());
+            Some(recorder)
+        }
+        Err(error) => {
+            eprintln!("Cannot record audio in {}: {}", path.display(), error);
+            None
+        }
+    };
+}
+
+fn stop_recording(recorder: AudioRecorder, nes: &mut Nes) {
+    match recorder.finish(nes) {
+        Ok(()) => println!("Audio recording stopped"),
+        Err(error) => eprintln!("Cannot write audio recording: {}", error),
+    }
+}
+
+fn record_audio(recorder: &mut Option<AudioRecorder>, samples: &[f32], nes: &mut Nes) {
+    if let Some(active_recorder) = recorder.as_mut() {
+        if let Err(error) = active_recorder.write(samples, nes) {
+            eprintln!("Cannot write audio recording, stopping it: {}", error);
+            *recorder = None;
+            nes.set_channel_capture(false);
+        }
+    }
+}
+
+//No window and no audio device: runs the requested frames as fast as possible
+fn run_headless(nes: &mut Nes, options: &Options, recorder: &mut Option<AudioRecorder>) {
+    let mut jam_reported = false;
+    for _ in 0..options.frames.unwrap_or(0) {
+        run_frame(nes);
+        report_cpu_jam(nes, &mut jam_reported);
+        let audio_samples = nes.take_audio_samples();
+        record_audio(recorder, &audio_samples, nes);
+    }
+}
+
+fn convert_keycode_to_channel(key: Option<Keycode>) -> Option<ApuChannel> {
+    match key {
+        Some(Keycode::F1) => Some(ApuChannel::Pulse1),
+        Some(Keycode::F2) => Some(ApuChannel::Pulse2),
+        Some(Keycode::F3) => Some(ApuChannel::Triangle),
+        Some(Keycode::F4) => Some(ApuChannel::Noise),
+        Some(Keycode::F5) => Some(ApuChannel::Dmc),
+        Some(Keycode::F6) => Some(ApuChannel::Expansion),
+        _ => None,
+    }
+}
+
+//F1 - F6 toggle the mute of a channel, with Shift they toggle the solo,
+//with Ctrl they lower the volume by 25% (and back to 100% after 0%)
+fn handle_mixer_hotkey(nes: &mut Nes, channel: ApuChannel, keymod: Mod) {
+    let name = channel.get_name();
+    if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+        let soloed = !nes.is_channel_soloed(channel);
+        nes.set_channel_soloed(channel, soloed);
+        println!("{} solo {}", name, if soloed { "on" } else { "off" });
+    } else if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) {
+        let volume = nes.get_channel_volume(channel) - 0.25;
+        let volume = if volume < -0.01 { 1.0 } else { volume.max(0.0) };
+        nes.set_channel_volume(channel, volume);
+        println!("{} volume {}%", name, (volume * 100.0).round());
+    } else {
+        let muted = !nes.is_channel_muted(channel);
+        nes.set_channel_muted(channel, muted);
+        println!("{} {}", name, if muted { "muted" } else { "unmuted" });
+    }
+}
+
+fn print_nsf_info(info: &NsfInfo) {
+    println!("{}", info.title);
+    println!("{}", info.artist);
+    println!("{}", info.copyright);
+    let chips = info.get_unsupported_chip_names();
+    if !chips.is_empty() {
+        eprintln!(
+            "Expansion audio is not supported, missing {}",
+            chips.join(", ")
+        );
+    }
+}
+
+//"Track 2/10: Title (2:35)", position is the index in the playlist
+fn get_nsf_track_label(info: &NsfInfo, position: usize) -> String {
+    let track = info.playlist[position];
+    let mut label = format!("Track {}/{}", position + 1, info.playlist.len());
+    if let Some(title) = info.get_track_title(track) {
+        label = format!("{}: {}", label, title);
+    }
+    if let Some(Some(length)) = info.track_lengths.get(track as usize) {
+        let seconds = length / 1000;
+        label = format!("{} ({}:{:02})", label, seconds / 60, seconds % 60);
+    }
+    label
+}
+
+//Moves in the playlist by step tracks (wrapping around) and starts the new track
+fn switch_nsf_track(
+    nes: &mut Nes,
+    canvas: &mut WindowCanvas,
+    info: &NsfInfo,
+    position: &mut usize,
+    step: isize,
+) {
+    let count = info.playlist.len() as isize;
+    *position = (*position as isize + step).rem_euclid(count) as usize;
+    nes.play_nsf_track(info.playlist[*position]);
+    show_nsf_track(canvas, info, *position);
+}
+
+fn show_nsf_track(canvas: &mut WindowCanvas, info: &NsfInfo, position: usize) {
+    let label = get_nsf_track_label(info, position);
+    println!("{}", label);
+    let title = if info.title.is_empty() {
+        format!("panznes - {}", label)
+    } else {
+        format!("{} - {}", info.title, label)
+    };
+    if let Err(error) = canvas.window_mut().set_title(&title) {
+        eprintln!("Cannot set window title: {}", error);
+    }
+}
+
 fn main() {
-    //TODO improve this part
     let args: Vec<String> = env::args().collect();
-
-    let path = &args.get(1).expect("Missing ROM filename");
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}\n{}", error, USAGE);
+            process::exit(1);
+        }
+    };
+    let path = &options.rom_path;
 
     let mut file = File::open(path).expect("Cannot open ROM file");
     let metadata = fs::metadata(path).expect("unable to read metadata");
     let mut buffer = vec![0; metadata.len() as usize];
     file.read(&mut buffer).expect("Error reading rom");
 
+    let loaded = if is_nsf(&buffer) {
+        from_nsf(&buffer).map(|(cart, info)| (cart, Some(info)))
+    } else {
+        from_ines(&buffer).map(|cart| (cart, None))
+    };
+    let (cart, nsf_info) = match loaded {
+        Ok(loaded) => loaded,
+        Err(error) => {
+            eprintln!("Cannot load ROM {}: {}", path, error);
+            process::exit(1);
+        }
+    };
+
+    let mut nes = Nes::create_nes(cart);
+    println!("{}", size_of::<Nes>());
+    //Position in the playlist of the NSF track being played
+    let mut nsf_position = 0;
+    match nsf_info.as_ref() {
+        Some(info) => {
+            print_nsf_info(info);
+            nes.load_nsf(info);
+            nsf_position = info
+                .playlist
+                .iter()
+                .position(|&track| track == info.starting_song)
+                .unwrap_or(0);
+            nes.play_nsf_track(info.playlist[nsf_position]);
+        }
+        None => nes.reset(),
+    }
+    let mut battery_save = BatterySave::load(path, &mut nes);
+
+    let record_path = match options.record_audio.as_ref() {
+        Some(record_path) => PathBuf::from(record_path),
+        None => Path::new(path).with_extension("wav"),
+    };
+    let mut recorder = if options.record_audio.is_some() {
+        start_recording(&record_path, &options, &mut nes)
+    } else {
+        None
+    };
+
+    if options.headless {
+        if let Some(info) = nsf_info.as_ref() {
+            println!("{}", get_nsf_track_label(info, nsf_position));
+        }
+        run_headless(&mut nes, &options, &mut recorder);
+        if let Some(recorder) = recorder {
+            stop_recording(recorder, &mut nes);
+        }
+        if let Some(save) = battery_save.as_mut() {
+            save.flush(&mut nes);
+        }
+        return;
+    }
+
     let sdl_context = sdl2::init().expect("Error init SDL2");
     let video_subsystem = sdl_context.video().expect("Error init SDL2 video");
 
+    let mut audio_output = if options.mute {
+        None
+    } else {
+        let audio_subsystem = sdl_context.audio().expect("Error init SDL2 audio");
+        match AudioOutput::new(&audio_subsystem, f64::from(CPU_FREQUENCY), options.volume) {
+            Ok(audio_output) => Some(audio_output),
+            Err(error) => {
+                eprintln!("Cannot open audio device, sound disabled: {}", error);
+                None
+            }
+        }
+    };
+
+    let pacing = match options.sync {
+        Some(PacingSource::Audio) | None if audio_output.is_some() => PacingSource::Audio,
+        Some(PacingSource::Audio) => {
+            eprintln!("No audio output, using timer pacing");
+            PacingSource::Timer
+        }
+        Some(pacing) => pacing,
+        None => PacingSource::Timer,
+    };
+
     let scaling = 3;
 
     let window = video_subsystem
@@ -88,29 +340,32 @@ fn main() {
         .build()
         .expect("Error init window");
 
-    let mut canvas = window.into_canvas().build().unwrap();
+    let mut canvas_builder = window.into_canvas();
+    if pacing == PacingSource::Vsync {
+        canvas_builder = canvas_builder.present_vsync();
+    }
+    let mut canvas = canvas_builder.build().unwrap();
     canvas.clear();
     canvas.present();
+    if let Some(info) = nsf_info.as_ref() {
+        show_nsf_track(&mut canvas, info, nsf_position);
+    }
 
-    let cart = from_ines(&buffer);
-
-    let mut nes = Nes::create_nes(cart);
-    println!("{}", size_of::<Nes>());
-    nes.reset();
-
-    let mut num_clock: u32 = 0;
-    const NUM_OP: u32 = 1789773;
     let mut execute = true;
+    let mut frames_since_flush = 0;
+    let mut jam_reported = false;
+    let mut frame_timer = FrameTimer::new();
 
     while execute == true {
-        let start = SystemTime::now();
-        while num_clock <= NUM_OP / 60 {
-            let cpu_clock = nes.execute_instruction();
-            nes.execute_ppu(cpu_clock);
-            num_clock += cpu_clock;
-        }
+        let frame_cycles = run_frame(&mut nes);
+        report_cpu_jam(&nes, &mut jam_reported);
 
         draw_screen(&mut canvas, &nes.screen);
+        let audio_samples = nes.take_audio_samples();
+        record_audio(&mut recorder, &audio_samples, &mut nes);
+        if let Some(audio_output) = audio_output.as_mut() {
+            audio_output.push_samples(&audio_samples);
+        }
 
         let mut event_pump = sdl_context.event_pump().unwrap();
 
@@ -125,25 +380,79 @@ fn main() {
                         nes.set_controller_status(nes_button.unwrap(), false)
                     }
                 }
-                Event::KeyDown { keycode, .. } => {
+                Event::KeyDown {
+                    keycode: Some(Keycode::F12),
+                    repeat: false,
+                    ..
+                } => {
+                    recorder = match recorder.take() {
+                        Some(active_recorder) => {
+                            stop_recording(active_recorder, &mut nes);
+                            None
+                        }
+                        None => start_recording(&record_path, &options, &mut nes),
+                    };
+                }
+                //Left and right switch tracks when playing a NSF file
+                Event::KeyDown {
+                    keycode: Some(keycode @ (Keycode::Left | Keycode::Right)),
+                    repeat,
+                    ..
+                } if nsf_info.is_some() => {
+                    if !repeat {
+                        let step = if keycode == Keycode::Left { -1 } else { 1 };
+                        if let Some(info) = nsf_info.as_ref() {
+                            switch_nsf_track(&mut nes, &mut canvas, info, &mut nsf_position, step);
+                        }
+                    }
+                }
+                Event::KeyDown {
+                    keycode,
+                    keymod,
+                    repeat,
+                    ..
+                } => {
                     let nes_button = convert_keycode_to_nes(keycode);
                     if nes_button.is_some() {
                         nes.set_controller_status(nes_button.unwrap(), true)
                     }
+                    if let Some(channel) = convert_keycode_to_channel(keycode) {
+                        if !repeat {
+                            handle_mixer_hotkey(&mut nes, channel, keymod);
+                        }
+                    }
                 }
                 _ => {}
             }
         }
 
-        num_clock -= NUM_OP / 60;
-        let end = SystemTime::now()
-            .duration_since(start)
-            .expect("Frame duration negative");
-        let delta_t = Duration::from_secs_f64(f64::from(1).div(f64::from(60)))
-            .checked_sub(end)
-            .unwrap_or_default();
-        if delta_t.as_millis() > 0 {
-            sleep(delta_t);
-        };
+        frames_since_flush += 1;
+        if frames_since_flush >= SAVE_FLUSH_FRAMES {
+            frames_since_flush = 0;
+            if let Some(save) = battery_save.as_mut() {
+                save.flush(&mut nes);
+            }
+        }
+
+        match pacing {
+            PacingSource::Audio => {
+                if let Some(audio_output) = audio_output.as_ref() {
+                    audio_output.wait_for_queue();
+                }
+            }
+            //Presenting the canvas already waited for the display
+            PacingSource::Vsync => {}
+            PacingSource::Timer => {
+                let frame_seconds = f64::from(frame_cycles) / f64::from(CPU_FREQUENCY);
+                frame_timer.wait(Duration::from_secs_f64(frame_seconds));
+            }
+        }
+    }
+
+    if let Some(recorder) = recorder {
+        stop_recording(recorder, &mut nes);
+    }
+    if let Some(save) = battery_save.as_mut() {
+        save.flush(&mut nes);
     }
 }
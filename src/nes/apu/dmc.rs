@@ -0,0 +1,148 @@
+//Timer periods in CPU cycles (NTSC)
+const DMC_RATES: [u16; 16] = [
+    428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
+];
+
+/*
+Delta modulation channel ($4010 - $4013):
+$4010: ILxx RRRR (IRQ enabled, loop, rate index)
+$4011: xDDD DDDD (direct load of the output level)
+$4012: AAAA AAAA (sample address = $C000 + A * 64)
+$4013: LLLL LLLL (sample length = L * 16 + 1 bytes)
+ */
+pub(super) struct Dmc {
+    irq_enabled: bool,
+    pub(super) irq_flag: bool,
+    loop_flag: bool,
+    rate: u16,
+    timer: u16,
+
+    //Output unit
+    output_level: u8,
+    shift_register: u8,
+    bits_remaining: u8,
+    silence: bool,
+    sample_buffer: Option<u8>,
+
+    //Memory reader
+    sample_address: u16,
+    sample_length: u16,
+    current_address: u16,
+    bytes_remaining: u16,
+}
+
+impl Dmc {
+    pub(super) fn new() -> Dmc {
+        Dmc {
+            irq_enabled: false,
+            irq_flag: false,
+            loop_flag: false,
+            rate: DMC_RATES[0],
+            timer: 0,
+            output_level: 0,
+            shift_register: 0,
+            bits_remaining: 8,
+            silence: true,
+            sample_buffer: None,
+            sample_address: 0xC000,
+            sample_length: 1,
+            current_address: 0xC000,
+            bytes_remaining: 0,
+        }
+    }
+
+    pub(super) fn write_register(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.irq_enabled = (value & 0x80) != 0;
+                if !self.irq_enabled {
+                    self.irq_flag = false;
+                }
+                self.loop_flag = (value & 0x40) != 0;
+                self.rate = DMC_RATES[(value & 0xF) as usize];
+            }
+            1 => self.output_level = value & 0x7F,
+            2 => self.sample_address = 0xC000 | (u16::from(value) << 6),
+            _ => self.sample_length = (u16::from(value) << 4) | 1,
+        }
+    }
+
+    //Bit 4 of $4015: restarts the sample if it has ended, or stops it
+    pub(super) fn set_enabled(&mut self, enabled: bool) {
+        self.irq_flag = false;
+        if !enabled {
+            self.bytes_remaining = 0;
+        } else if self.bytes_remaining == 0 {
+            self.restart();
+        }
+    }
+
+    fn restart(&mut self) {
+        self.current_address = self.sample_address;
+        self.bytes_remaining = self.sample_length;
+    }
+
+    //The memory reader refills the sample buffer as soon as it's empty
+    pub(super) fn needs_sample(&self) -> bool {
+        self.sample_buffer.is_none() && self.bytes_remaining > 0
+    }
+
+    pub(super) fn get_current_address(&self) -> u16 {
+        self.current_address
+    }
+
+    pub(super) fn load_sample(&mut self, sample: u8) {
+        self.sample_buffer = Some(sample);
+        //The address wraps to $8000 after $FFFF
+        self.current_address = self.current_address.wrapping_add(1) | 0x8000;
+        self.bytes_remaining -= 1;
+
+        if self.bytes_remaining == 0 {
+            if self.loop_flag {
+                self.restart();
+            } else if self.irq_enabled {
+                self.irq_flag = true;
+            }
+        }
+    }
+
+    pub(super) fn is_active(&self) -> bool {
+        self.bytes_remaining > 0
+    }
+
+    pub(super) fn clock_timer(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+        self.timer = self.rate - 1;
+
+        //Each bit of the sample moves the output level by 2, without wrapping
+        if !self.silence {
+            if (self.shift_register & 0x1) != 0 {
+                if self.output_level <= 125 {
+                    self.output_level += 2;
+                }
+            } else if self.output_level >= 2 {
+                self.output_level -= 2;
+            }
+        }
+        self.shift_register = self.shift_register >> 1;
+
+        self.bits_remaining -= 1;
+        if self.bits_remaining == 0 {
+            self.bits_remaining = 8;
+            match self.sample_buffer.take() {
+                Some(sample) => {
+                    self.silence = false;
+                    self.shift_register = sample;
+                }
+                None => self.silence = true,
+            }
+        }
+    }
+
+    pub(super) fn output(&self) -> u8 {
+        self.output_level
+    }
+}
@@ -0,0 +1,118 @@
+/*
+Frame counter ($4017):
+7  bit  0
+---- ----
+MIxx xxxx
+||
+|+-------- IRQ inhibit flag
++--------- Sequencer mode: 0 = 4-step, 1 = 5-step
+
+Steps in CPU cycles after the reset of the sequencer (NTSC):
+4-step: 7457 Q, 14913 Q+H, 22371 Q, 29829 Q+H (+ IRQ on 29828 - 29830), period 29830
+5-step: 7457 Q, 14913 Q+H, 22371 Q, 37281 Q+H, period 37282
+Q = quarter frame (envelopes, linear counter), H = half frame (length counters, sweeps)
+ */
+pub(super) enum FrameClock {
+    None,
+    Quarter,
+    Half,
+}
+
+pub(super) struct FrameCounter {
+    five_step_mode: bool,
+    irq_inhibit: bool,
+    pub(super) irq_flag: bool,
+    cycle: u32,
+
+    last_value: u8,
+    //The sequencer is reset 3 or 4 CPU cycles after the write: (value, cycles left)
+    pending_write: Option<(u8, u32)>,
+}
+
+impl FrameCounter {
+    pub(super) fn new() -> FrameCounter {
+        FrameCounter {
+            five_step_mode: false,
+            irq_inhibit: false,
+            irq_flag: false,
+            cycle: 0,
+            last_value: 0,
+            pending_write: None,
+        }
+    }
+
+    //apu_cycle is set when the write happens on the same cycle as the pulse timers clock
+    pub(super) fn write(&mut self, value: u8, apu_cycle: bool) {
+        self.last_value = value;
+        self.irq_inhibit = (value & 0x40) != 0;
+        if self.irq_inhibit {
+            self.irq_flag = false;
+        }
+
+        let delay = if apu_cycle { 3 } else { 4 };
+        self.pending_write = Some((value, delay));
+    }
+
+    //On reset, the frame counter behaves as if the last value was written again
+    pub(super) fn reset(&mut self) {
+        self.irq_flag = false;
+        self.write(self.last_value, true);
+    }
+
+    pub(super) fn clock(&mut self) -> FrameClock {
+        if let Some((value, delay)) = self.pending_write {
+            if delay > 1 {
+                self.pending_write = Some((value, delay - 1));
+            } else {
+                self.pending_write = None;
+                self.five_step_mode = (value & 0x80) != 0;
+                self.cycle = 0;
+                //Switching to the 5-step mode clocks the units immediately
+                return if self.five_step_mode {
+                    FrameClock::Half
+                } else {
+                    FrameClock::None
+                };
+            }
+        }
+
+        self.cycle += 1;
+        if self.five_step_mode {
+            return match self.cycle {
+                7457 | 22371 => FrameClock::Quarter,
+                14913 => FrameClock::Half,
+                37281 => FrameClock::Half,
+                37282 => {
+                    self.cycle = 0;
+                    FrameClock::None
+                }
+                _ => FrameClock::None,
+            };
+        }
+
+        return match self.cycle {
+            7457 | 22371 => FrameClock::Quarter,
+            14913 => FrameClock::Half,
+            29828 => {
+                self.set_irq_flag();
+                FrameClock::None
+            }
+            29829 => {
+                self.set_irq_flag();
+                FrameClock::Half
+            }
+            29830 => {
+                self.set_irq_flag();
+                self.cycle = 0;
+                FrameClock::None
+            }
+            _ => FrameClock::None,
+        };
+    }
+
+    fn set_irq_flag(&mut self) {
+        if !self.irq_inhibit {
+            self.irq_flag = true;
+        }
+    }
+}
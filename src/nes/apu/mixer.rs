@@ -0,0 +1,82 @@
+use crate::nes::ApuChannel;
+
+//The 5 channels of the 2A03 and the expansion audio of the cartridge
+pub(super) const MIXER_CHANNEL_COUNT: usize = 6;
+
+//Mute, solo and volume of each channel, set from the frontend
+pub(super) struct Mixer {
+    volumes: [f32; MIXER_CHANNEL_COUNT],
+    muted: [bool; MIXER_CHANNEL_COUNT],
+    soloed: [bool; MIXER_CHANNEL_COUNT],
+    //Gain applied to each channel, updated when the controls change
+    pub(super) gains: [f32; MIXER_CHANNEL_COUNT],
+}
+
+impl Mixer {
+    pub(super) fn new() -> Mixer {
+        Mixer {
+            volumes: [1.0; MIXER_CHANNEL_COUNT],
+            muted: [false; MIXER_CHANNEL_COUNT],
+            soloed: [false; MIXER_CHANNEL_COUNT],
+            gains: [1.0; MIXER_CHANNEL_COUNT],
+        }
+    }
+
+    pub(super) fn set_volume(&mut self, channel: ApuChannel, volume: f32) {
+        self.volumes[channel as usize] = volume.max(0.0);
+        self.update_gains();
+    }
+
+    pub(super) fn get_volume(&self, channel: ApuChannel) -> f32 {
+        self.volumes[channel as usize]
+    }
+
+    pub(super) fn set_muted(&mut self, channel: ApuChannel, muted: bool) {
+        self.muted[channel as usize] = muted;
+        self.update_gains();
+    }
+
+    pub(super) fn is_muted(&self, channel: ApuChannel) -> bool {
+        self.muted[channel as usize]
+    }
+
+    pub(super) fn set_soloed(&mut self, channel: ApuChannel, soloed: bool) {
+        self.soloed[channel as usize] = soloed;
+        self.update_gains();
+    }
+
+    pub(super) fn is_soloed(&self, channel: ApuChannel) -> bool {
+        self.soloed[channel as usize]
+    }
+
+    //When some channels are soloed, only them are heard (even if muted)
+    fn update_gains(&mut self) {
+        let any_soloed = self.soloed.iter().any(|&soloed| soloed);
+        for channel in 0..MIXER_CHANNEL_COUNT {
+            let audible = if any_soloed {
+                self.soloed[channel]
+            } else {
+                !self.muted[channel]
+            };
+            self.gains[channel] = if audible { self.volumes[channel] } else { 0.0 };
+        }
+    }
+}
+
+//Nonlinear mixer of the 2A03: pulse is the sum of the 2 pulse channels (0 - 30)
+pub(super) fn mix_pulse(pulse: f32) -> f32 {
+    if pulse == 0.0 {
+        0.0
+    } else {
+        95.88 / (8128.0 / pulse + 100.0)
+    }
+}
+
+pub(super) fn mix_tnd(triangle: f32, noise: f32, dmc: f32) -> f32 {
+    let tnd = triangle / 8227.0 + noise / 12241.0 + dmc / 22638.0;
+    if tnd == 0.0 {
+        0.0
+    } else {
+        159.79 / (1.0 / tnd + 100.0)
+    }
+}
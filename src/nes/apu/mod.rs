@@ -0,0 +1,341 @@
+use crate::nes::apu::dmc::Dmc;
+use crate::nes::apu::frame_counter::{FrameClock, FrameCounter};
+use crate::nes::apu::mixer::{mix_pulse, mix_tnd, Mixer, MIXER_CHANNEL_COUNT};
+use crate::nes::apu::noise::Noise;
+use crate::nes::apu::pulse::Pulse;
+use crate::nes::apu::triangle::Triangle;
+use crate::nes::{ApuChannel, IrqSource, Nes};
+
+mod dmc;
+mod frame_counter;
+mod mixer;
+mod noise;
+mod pulse;
+mod triangle;
+mod units;
+
+//The CPU is halted while the DMC memory reader takes the bus
+const DMC_DMA_CYCLES: u32 = 4;
+
+pub(crate) struct Apu {
+    pulse_1: Pulse,
+    pulse_2: Pulse,
+    triangle: Triangle,
+    noise: Noise,
+    dmc: Dmc,
+    frame_counter: FrameCounter,
+    mixer: Mixer,
+
+    //Pulse timers are clocked every other CPU cycle
+    odd_cycle: bool,
+    //Output of the cartridge sound chip, already scaled to its relative level
+    expansion_output: f32,
+
+    //Mixer output, one sample per CPU cycle
+    samples: Vec<f32>,
+    //Output of each channel alone, indexed by ApuChannel (only while capture_channels is set)
+    capture_channels: bool,
+    channel_samples: Vec<Vec<f32>>,
+}
+
+impl Apu {
+    pub(crate) fn new() -> Apu {
+        Apu {
+            pulse_1: Pulse::new(true),
+            pulse_2: Pulse::new(false),
+            triangle: Triangle::new(),
+            noise: Noise::new(),
+            dmc: Dmc::new(),
+            frame_counter: FrameCounter::new(),
+            mixer: Mixer::new(),
+            odd_cycle: false,
+            expansion_output: 0.0,
+            samples: Vec::new(),
+            capture_channels: false,
+            channel_samples: vec![Vec::new(); MIXER_CHANNEL_COUNT],
+        }
+    }
+
+    //addr is in 0x4000 - 0x4013
+    pub(crate) fn write_register(&mut self, addr: u16, value: u8) {
+        let register = addr & 0x3;
+        match addr {
+            0x4000..=0x4003 => self.pulse_1.write_register(register, value),
+            0x4004..=0x4007 => self.pulse_2.write_register(register, value),
+            0x4008..=0x400B => self.triangle.write_register(register, value),
+            0x400C..=0x400F => self.noise.write_register(register, value),
+            _ => self.dmc.write_register(register, value),
+        }
+    }
+
+    /*
+    $4015 read:
+    7  bit  0
+    ---- ----
+    IFxD NT21
+    || | ||||
+    || | |||+- Pulse 1 length counter > 0
+    || | ||+-- Pulse 2 length counter > 0
+    || | |+--- Triangle length counter > 0
+    || | +---- Noise length counter > 0
+    || +------ DMC bytes remaining > 0
+    |+-------- Frame interrupt
+    +--------- DMC interrupt
+     */
+    pub(crate) fn read_status(&mut self) -> u8 {
+        let mut status = 0;
+        if self.pulse_1.length_counter.is_active() {
+            status = status | 0x01;
+        }
+        if self.pulse_2.length_counter.is_active() {
+            status = status | 0x02;
+        }
+        if self.triangle.length_counter.is_active() {
+            status = status | 0x04;
+        }
+        if self.noise.length_counter.is_active() {
+            status = status | 0x08;
+        }
+        if self.dmc.is_active() {
+            status = status | 0x10;
+        }
+        if self.dmc.irq_flag {
+            status = status | 0x80;
+        }
+        //Reading the status acknowledges the frame interrupt
+        if self.frame_counter.irq_flag {
+            status = status | 0x40;
+            self.frame_counter.irq_flag = false;
+        }
+        status
+    }
+
+    //$4015 write: xxxD NT21, enables each channel
+    pub(crate) fn write_status(&mut self, value: u8) {
+        self.pulse_1.length_counter.set_enabled((value & 0x01) != 0);
+        self.pulse_2.length_counter.set_enabled((value & 0x02) != 0);
+        self.triangle
+            .length_counter
+            .set_enabled((value & 0x04) != 0);
+        self.noise.length_counter.set_enabled((value & 0x08) != 0);
+        self.dmc.set_enabled((value & 0x10) != 0);
+    }
+
+    pub(crate) fn write_frame_counter(&mut self, value: u8) {
+        self.frame_counter.write(value, self.odd_cycle);
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.write_status(0);
+        self.frame_counter.reset();
+    }
+
+    //expansion_output is the output of the cartridge for this cycle
+    fn clock(&mut self, expansion_output: f32) {
+        self.expansion_output = expansion_output;
+        self.triangle.clock_timer();
+        self.noise.clock_timer();
+        self.dmc.clock_timer();
+        if self.odd_cycle {
+            self.pulse_1.clock_timer();
+            self.pulse_2.clock_timer();
+        }
+        self.odd_cycle = !self.odd_cycle;
+
+        match self.frame_counter.clock() {
+            FrameClock::Quarter => self.clock_quarter_frame(),
+            FrameClock::Half => {
+                self.clock_quarter_frame();
+                self.clock_half_frame();
+            }
+            FrameClock::None => {}
+        }
+        let sample = self.mix();
+        self.samples.push(sample);
+
+        if self.capture_channels {
+            let levels = self.get_channel_levels();
+            for (buffer, level) in self.channel_samples.iter_mut().zip(levels) {
+                buffer.push(level);
+            }
+        }
+    }
+
+    fn clock_quarter_frame(&mut self) {
+        self.pulse_1.clock_quarter_frame();
+        self.pulse_2.clock_quarter_frame();
+        self.triangle.clock_quarter_frame();
+        self.noise.clock_quarter_frame();
+    }
+
+    fn clock_half_frame(&mut self) {
+        self.pulse_1.clock_half_frame();
+        self.pulse_2.clock_half_frame();
+        self.triangle.clock_half_frame();
+        self.noise.clock_half_frame();
+    }
+
+    //Nonlinear mixer of the 2A03, the output is between 0.0 and 1.0 (at full volume)
+    fn mix(&self) -> f32 {
+        let gains = &self.mixer.gains;
+        let pulse_out = mix_pulse(
+            f32::from(self.pulse_1.output()) * gains[ApuChannel::Pulse1 as usize]
+                + f32::from(self.pulse_2.output()) * gains[ApuChannel::Pulse2 as usize],
+        );
+        let tnd_out = mix_tnd(
+            f32::from(self.triangle.output()) * gains[ApuChannel::Triangle as usize],
+            f32::from(self.noise.output()) * gains[ApuChannel::Noise as usize],
+            f32::from(self.dmc.output()) * gains[ApuChannel::Dmc as usize],
+        );
+        let expansion_out = self.expansion_output * gains[ApuChannel::Expansion as usize];
+        pulse_out + tnd_out + expansion_out
+    }
+
+    //What each channel would sound like if it was the only one playing
+    fn get_channel_levels(&self) -> [f32; MIXER_CHANNEL_COUNT] {
+        [
+            mix_pulse(f32::from(self.pulse_1.output())),
+            mix_pulse(f32::from(self.pulse_2.output())),
+            mix_tnd(f32::from(self.triangle.output()), 0.0, 0.0),
+            mix_tnd(0.0, f32::from(self.noise.output()), 0.0),
+            mix_tnd(0.0, 0.0, f32::from(self.dmc.output())),
+            self.expansion_output,
+        ]
+    }
+}
+
+impl Nes {
+    pub fn execute_apu(&mut self, cpu_cycles: u32) {
+        //Part of the instruction may have already been run by sync_apu
+        let first_cycle = self.apu_synced_cycles;
+        let cpu_cycles = cpu_cycles.saturating_sub(first_cycle);
+        self.apu_synced_cycles = 0;
+        self.run_apu(first_cycle, cpu_cycles);
+    }
+
+    //Catches up the APU with the CPU cycles spent by the current instruction before
+    //the bus access in progress, like sync_ppu
+    fn sync_apu(&mut self) {
+        let elapsed_cycles = self.cpu_bus_accesses.saturating_sub(1);
+        let first_cycle = self.apu_synced_cycles;
+        let pending_cycles = elapsed_cycles.saturating_sub(first_cycle);
+        self.apu_synced_cycles += pending_cycles;
+        self.run_apu(first_cycle, pending_cycles);
+    }
+
+    //first_cycle is the position in the current instruction of the first cycle to run
+    fn run_apu(&mut self, first_cycle: u32, cpu_cycles: u32) {
+        for cycle in first_cycle..first_cycle + cpu_cycles {
+            let expansion_output = match self.cartridge.get_expansion_audio() {
+                Some(audio) => {
+                    audio.clock();
+                    audio.get_sample() * audio.get_relative_level()
+                }
+                None => 0.0,
+            };
+            self.apu.clock(expansion_output);
+            if self.apu.dmc.needs_sample() {
+                self.fetch_dmc_sample(cycle);
+            }
+        }
+        self.update_apu_irq();
+    }
+
+    /*
+    The DMC memory reader reads the sample bytes through the CPU bus and stalls the CPU.
+    If the CPU was reading the controller port at that moment, the read is repeated and
+    the controller shifts an extra bit, which is lost.
+     */
+    fn fetch_dmc_sample(&mut self, cycle: u32) {
+        //This read is not part of the instruction timing
+        let cpu_bus_accesses = self.cpu_bus_accesses;
+        let sample = self.read_cpu_byte(self.apu.dmc.get_current_address());
+        self.cpu_bus_accesses = cpu_bus_accesses;
+
+        self.apu.dmc.load_sample(sample);
+        self.dmc_stall_cycles += DMC_DMA_CYCLES;
+
+        if self.controller_read_cycle == Some(cycle) {
+            self.read_controller_port();
+        }
+    }
+
+    fn update_apu_irq(&mut self) {
+        if self.apu.frame_counter.irq_flag {
+            self.set_irq(IrqSource::FrameCounter);
+        } else {
+            self.clear_irq(IrqSource::FrameCounter);
+        }
+        if self.apu.dmc.irq_flag {
+            self.set_irq(IrqSource::Dmc);
+        } else {
+            self.clear_irq(IrqSource::Dmc);
+        }
+    }
+
+    pub(crate) fn write_apu_register(&mut self, addr: u16, value: u8) {
+        self.apu.write_register(addr, value);
+        self.update_apu_irq();
+    }
+
+    pub(crate) fn write_apu_status(&mut self, value: u8) {
+        self.apu.write_status(value);
+        self.update_apu_irq();
+    }
+
+    pub(crate) fn read_apu_status(&mut self) -> u8 {
+        self.sync_apu();
+        let status = self.apu.read_status();
+        self.update_apu_irq();
+        status
+    }
+
+    pub(crate) fn write_apu_frame_counter(&mut self, value: u8) {
+        self.sync_apu();
+        self.apu.write_frame_counter(value);
+        self.update_apu_irq();
+    }
+
+    //Returns the samples generated since the last call, at the CPU clock rate
+    pub fn take_audio_samples(&mut self) -> Vec<f32> {
+        std::mem::take(&mut self.apu.samples)
+    }
+
+    //Enables the capture of each channel alone, read with take_channel_samples
+    pub fn set_channel_capture(&mut self, enabled: bool) {
+        self.apu.capture_channels = enabled;
+        if !enabled {
+            for buffer in self.apu.channel_samples.iter_mut() {
+                buffer.clear();
+            }
+        }
+    }
+
+    pub fn take_channel_samples(&mut self, channel: ApuChannel) -> Vec<f32> {
+        std::mem::take(&mut self.apu.channel_samples[channel as usize])
+    }
+
+    pub fn set_channel_volume(&mut self, channel: ApuChannel, volume: f32) {
+        self.apu.mixer.set_volume(channel, volume);
+    }
+
+    pub fn get_channel_volume(&self, channel: ApuChannel) -> f32 {
+        self.apu.mixer.get_volume(channel)
+    }
+
+    pub fn set_channel_muted(&mut self, channel: ApuChannel, muted: bool) {
+        self.apu.mixer.set_muted(channel, muted);
+    }
+
+    pub fn is_channel_muted(&self, channel: ApuChannel) -> bool {
+        self.apu.mixer.is_muted(channel)
+    }
+
+    pub fn set_channel_soloed(&mut self, channel: ApuChannel, soloed: bool) {
+        self.apu.mixer.set_soloed(channel, soloed);
+    }
+
+    pub fn is_channel_soloed(&self, channel: ApuChannel) -> bool {
+        self.apu.mixer.is_soloed(channel)
+    }
+}
@@ -0,0 +1,82 @@
+use crate::nes::apu::units::{Envelope, LengthCounter};
+
+//Timer periods in CPU cycles (NTSC)
+const NOISE_PERIODS: [u16; 16] = [
+    4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068,
+];
+
+/*
+Noise channel ($400C - $400F):
+$400C: xxLC VVVV (envelope)
+$400E: Mxxx PPPP (mode, period index)
+$400F: llll lxxx (length counter load)
+ */
+pub(super) struct Noise {
+    mode: bool,
+    timer_period: u16,
+    timer: u16,
+    //15 bits linear feedback shift register
+    shift_register: u16,
+
+    pub(super) envelope: Envelope,
+    pub(super) length_counter: LengthCounter,
+}
+
+impl Noise {
+    pub(super) fn new() -> Noise {
+        Noise {
+            mode: false,
+            timer_period: NOISE_PERIODS[0],
+            timer: 0,
+            shift_register: 1,
+            envelope: Envelope::new(),
+            length_counter: LengthCounter::new(),
+        }
+    }
+
+    pub(super) fn write_register(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.length_counter.halted = (value & 0x20) != 0;
+                self.envelope.write(value);
+            }
+            1 => {}
+            2 => {
+                self.mode = (value & 0x80) != 0;
+                self.timer_period = NOISE_PERIODS[(value & 0xF) as usize];
+            }
+            _ => {
+                self.length_counter.load(value >> 3);
+                self.envelope.start = true;
+            }
+        }
+    }
+
+    pub(super) fn clock_timer(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+        self.timer = self.timer_period - 1;
+
+        //Mode 1 takes the feedback from bit 6, giving a shorter (metallic) sequence
+        let tap = if self.mode { 6 } else { 1 };
+        let feedback = (self.shift_register ^ (self.shift_register >> tap)) & 0x1;
+        self.shift_register = (self.shift_register >> 1) | (feedback << 14);
+    }
+
+    pub(super) fn clock_quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub(super) fn clock_half_frame(&mut self) {
+        self.length_counter.clock();
+    }
+
+    pub(super) fn output(&self) -> u8 {
+        if !self.length_counter.is_active() || (self.shift_register & 0x1) != 0 {
+            return 0;
+        }
+        self.envelope.output()
+    }
+}
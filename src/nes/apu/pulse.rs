@@ -0,0 +1,140 @@
+use crate::nes::apu::units::{Envelope, LengthCounter};
+
+//The sequencer counts down, so the sequences are read as 0, 7, 6, 5...
+const DUTY_SEQUENCES: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+/*
+Pulse channel ($4000 - $4003 and $4004 - $4007):
+$4000: DDLC VVVV (duty, envelope)
+$4001: EPPP NSSS (sweep enabled, period, negate, shift)
+$4002: LLLL LLLL (timer low)
+$4003: llll lHHH (length counter load, timer high)
+ */
+pub(super) struct Pulse {
+    //Pulse 1 negates the sweep change with one's complement, pulse 2 with two's complement
+    ones_complement_negate: bool,
+
+    duty: usize,
+    sequence_step: usize,
+    timer_period: u16,
+    timer: u16,
+
+    sweep_enabled: bool,
+    sweep_period: u8,
+    sweep_negate: bool,
+    sweep_shift: u8,
+    sweep_divider: u8,
+    sweep_reload: bool,
+
+    pub(super) envelope: Envelope,
+    pub(super) length_counter: LengthCounter,
+}
+
+impl Pulse {
+    pub(super) fn new(ones_complement_negate: bool) -> Pulse {
+        Pulse {
+            ones_complement_negate,
+            duty: 0,
+            sequence_step: 0,
+            timer_period: 0,
+            timer: 0,
+            sweep_enabled: false,
+            sweep_period: 0,
+            sweep_negate: false,
+            sweep_shift: 0,
+            sweep_divider: 0,
+            sweep_reload: false,
+            envelope: Envelope::new(),
+            length_counter: LengthCounter::new(),
+        }
+    }
+
+    pub(super) fn write_register(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.duty = (value >> 6) as usize;
+                self.length_counter.halted = (value & 0x20) != 0;
+                self.envelope.write(value);
+            }
+            1 => {
+                self.sweep_enabled = (value & 0x80) != 0;
+                self.sweep_period = (value >> 4) & 0x7;
+                self.sweep_negate = (value & 0x08) != 0;
+                self.sweep_shift = value & 0x7;
+                self.sweep_reload = true;
+            }
+            2 => self.timer_period = (self.timer_period & 0x700) | u16::from(value),
+            _ => {
+                self.timer_period = (self.timer_period & 0xFF) | (u16::from(value & 0x7) << 8);
+                self.length_counter.load(value >> 3);
+                self.sequence_step = 0;
+                self.envelope.start = true;
+            }
+        }
+    }
+
+    //Clocked every APU cycle (2 CPU cycles)
+    pub(super) fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+            self.sequence_step = (self.sequence_step + 7) & 0x7;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub(super) fn clock_quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub(super) fn clock_half_frame(&mut self) {
+        self.length_counter.clock();
+
+        if self.sweep_divider == 0
+            && self.sweep_enabled
+            && self.sweep_shift > 0
+            && !self.is_sweep_muting()
+        {
+            self.timer_period = self.get_sweep_target_period();
+        }
+        if self.sweep_divider == 0 || self.sweep_reload {
+            self.sweep_divider = self.sweep_period;
+            self.sweep_reload = false;
+        } else {
+            self.sweep_divider -= 1;
+        }
+    }
+
+    fn get_sweep_target_period(&self) -> u16 {
+        let change = self.timer_period >> self.sweep_shift;
+        if !self.sweep_negate {
+            return self.timer_period + change;
+        }
+
+        if self.ones_complement_negate {
+            self.timer_period.saturating_sub(change + 1)
+        } else {
+            self.timer_period.saturating_sub(change)
+        }
+    }
+
+    //The sweep unit mutes the channel even when it's disabled
+    fn is_sweep_muting(&self) -> bool {
+        self.timer_period < 8 || self.get_sweep_target_period() > 0x7FF
+    }
+
+    pub(super) fn output(&self) -> u8 {
+        if !self.length_counter.is_active()
+            || self.is_sweep_muting()
+            || DUTY_SEQUENCES[self.duty][self.sequence_step] == 0
+        {
+            return 0;
+        }
+        self.envelope.output()
+    }
+}
@@ -0,0 +1,90 @@
+use crate::nes::apu::units::LengthCounter;
+
+const TRIANGLE_SEQUENCE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12,
+    13, 14, 15,
+];
+
+/*
+Triangle channel ($4008 - $400B):
+$4008: CRRR RRRR (length counter halt / linear counter control, linear counter reload value)
+$400A: LLLL LLLL (timer low)
+$400B: llll lHHH (length counter load, timer high)
+ */
+pub(super) struct Triangle {
+    control: bool,
+    linear_reload_value: u8,
+    linear_counter: u8,
+    linear_reload: bool,
+
+    timer_period: u16,
+    timer: u16,
+    sequence_step: usize,
+
+    pub(super) length_counter: LengthCounter,
+}
+
+impl Triangle {
+    pub(super) fn new() -> Triangle {
+        Triangle {
+            control: false,
+            linear_reload_value: 0,
+            linear_counter: 0,
+            linear_reload: false,
+            timer_period: 0,
+            timer: 0,
+            sequence_step: 0,
+            length_counter: LengthCounter::new(),
+        }
+    }
+
+    pub(super) fn write_register(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.control = (value & 0x80) != 0;
+                self.length_counter.halted = self.control;
+                self.linear_reload_value = value & 0x7F;
+            }
+            1 => {}
+            2 => self.timer_period = (self.timer_period & 0x700) | u16::from(value),
+            _ => {
+                self.timer_period = (self.timer_period & 0xFF) | (u16::from(value & 0x7) << 8);
+                self.length_counter.load(value >> 3);
+                self.linear_reload = true;
+            }
+        }
+    }
+
+    //Unlike the other channels, the timer is clocked every CPU cycle
+    pub(super) fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+            //The sequencer stops (and holds its output) while a counter is 0
+            if self.length_counter.is_active() && self.linear_counter > 0 {
+                self.sequence_step = (self.sequence_step + 1) & 0x1F;
+            }
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub(super) fn clock_quarter_frame(&mut self) {
+        if self.linear_reload {
+            self.linear_counter = self.linear_reload_value;
+        } else if self.linear_counter > 0 {
+            self.linear_counter -= 1;
+        }
+
+        if !self.control {
+            self.linear_reload = false;
+        }
+    }
+
+    pub(super) fn clock_half_frame(&mut self) {
+        self.length_counter.clock();
+    }
+
+    pub(super) fn output(&self) -> u8 {
+        TRIANGLE_SEQUENCE[self.sequence_step]
+    }
+}
@@ -0,0 +1,112 @@
+//Values loaded in the length counters, indexed by the top 5 bits of the last channel register
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14, 12, 16, 24, 18, 48, 20, 96, 22,
+    192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+/*
+Envelope of pulse and noise channels, clocked by the quarter frames:
+7  bit  0
+---- ----
+xxLC VVVV
+  || ||||
+  || ++++- Constant volume, or period of the decay divider
+  |+------ Constant volume flag
+  +------- Loop the decay (also halts the length counter)
+ */
+pub(super) struct Envelope {
+    pub(super) start: bool,
+    loop_flag: bool,
+    constant_volume: bool,
+    volume: u8,
+    divider: u8,
+    decay: u8,
+}
+
+impl Envelope {
+    pub(super) fn new() -> Envelope {
+        Envelope {
+            start: false,
+            loop_flag: false,
+            constant_volume: false,
+            volume: 0,
+            divider: 0,
+            decay: 0,
+        }
+    }
+
+    pub(super) fn write(&mut self, value: u8) {
+        self.loop_flag = (value & 0x20) != 0;
+        self.constant_volume = (value & 0x10) != 0;
+        self.volume = value & 0xF;
+    }
+
+    pub(super) fn clock(&mut self) {
+        if self.start {
+            self.start = false;
+            self.decay = 15;
+            self.divider = self.volume;
+            return;
+        }
+
+        if self.divider == 0 {
+            self.divider = self.volume;
+            if self.decay > 0 {
+                self.decay -= 1;
+            } else if self.loop_flag {
+                self.decay = 15;
+            }
+        } else {
+            self.divider -= 1;
+        }
+    }
+
+    pub(super) fn output(&self) -> u8 {
+        if self.constant_volume {
+            self.volume
+        } else {
+            self.decay
+        }
+    }
+}
+
+//Silences the channel when it reaches 0, clocked by the half frames
+pub(super) struct LengthCounter {
+    enabled: bool,
+    pub(super) halted: bool,
+    counter: u8,
+}
+
+impl LengthCounter {
+    pub(super) fn new() -> LengthCounter {
+        LengthCounter {
+            enabled: false,
+            halted: false,
+            counter: 0,
+        }
+    }
+
+    //Disabling the channel in $4015 clears the counter
+    pub(super) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.counter = 0;
+        }
+    }
+
+    pub(super) fn load(&mut self, index: u8) {
+        if self.enabled {
+            self.counter = LENGTH_TABLE[index as usize & 0x1F];
+        }
+    }
+
+    pub(super) fn clock(&mut self) {
+        if !self.halted && self.counter > 0 {
+            self.counter -= 1;
+        }
+    }
+
+    pub(super) fn is_active(&self) -> bool {
+        self.counter > 0
+    }
+}
@@ -1,11 +1,12 @@
 use crate::memory::Memory;
 use crate::nes::cpu::opcodes::OPCODES;
-use crate::nes::ppu::registers::{NMI_ENABLED, V_BLANK};
 use crate::nes::{
-    Interrupt, Nes, BREAK_FLAG, CARRY, DECIMAL_MODE, IRQ_DISABLE, NEGATIV, OVERFLOW, UNUSED, ZERO,
+    Interrupt, IrqSource, Nes, BREAK_FLAG, CARRY, DECIMAL_MODE, IRQ_DISABLE, NEGATIV, OVERFLOW,
+    UNUSED, ZERO,
 };
 
 mod opcodes;
+mod unofficial_opcodes;
 
 impl Memory for Nes {
     fn read_byte(&mut self, addr: u16) -> u8 {
@@ -19,10 +20,44 @@ impl Memory for Nes {
 
 impl Nes {
     pub fn execute_instruction(&mut self) -> u32 {
-        if (self.ppuctrl & NMI_ENABLED) != 0 && (self.ppustatus & V_BLANK) != 0 {
-            self.ppuctrl = self.ppuctrl & !NMI_ENABLED;
-            self.ppustatus = self.ppustatus & !V_BLANK;
-            return self.raise_interrupt(Interrupt::NMI);
+        //The DMC fetches done during the previous instruction stalled the CPU:
+        //these cycles come before the bus accesses of this instruction
+        let stall_cycles = self.dmc_stall_cycles;
+        self.dmc_stall_cycles = 0;
+        self.cpu_bus_accesses = stall_cycles;
+        self.controller_read_cycle = None;
+
+        let cycles = stall_cycles + self.run_instruction();
+        self.cartridge.clock_cpu(cycles);
+        self.clock_nsf_player(cycles);
+        return cycles;
+    }
+
+    fn run_instruction(&mut self) -> u32 {
+        //A jammed CPU doesn't fetch anything anymore, only a reset can restart it
+        if self.cpu_jam.is_some() {
+            return 1;
+        }
+
+        //The NMI edge has been latched by update_nmi_output
+        if self.nmi_pending {
+            if self.nmi_delayed {
+                self.nmi_delayed = false;
+            } else {
+                self.nmi_pending = false;
+                return self.raise_interrupt(Interrupt::NMI);
+            }
+        }
+
+        if self.cartridge.is_irq_requested() {
+            self.set_irq(IrqSource::Mapper);
+        } else {
+            self.clear_irq(IrqSource::Mapper);
+        }
+
+        //The IRQ is level triggered: it's serviced as long as it's asserted and not masked
+        if self.irq_line != 0 && !self.irq_poll_disabled {
+            return self.raise_interrupt(Interrupt::IRQ);
         }
 
         if self.request_dma == true {
@@ -31,6 +66,11 @@ impl Nes {
             return 512;
         }
 
+        //A NSF player waits between the calls of INIT and PLAY
+        if let Some(idle_cycles) = self.step_nsf_player() {
+            return idle_cycles;
+        }
+
         let opcode = self.read_byte(self.prog_counter);
         let instruction = &OPCODES[opcode as usize];
         /*println!(
@@ -38,6 +78,7 @@ impl Nes {
             self.prog_counter, opcode, self.a, self.x, self.y, self.stack_ptr, self.flag
         );*/
         self.prog_counter = self.prog_counter.wrapping_add(1);
+        let irq_disabled_before = (self.flag & IRQ_DISABLE) != 0;
 
         let cycles = match opcode {
             //Register/Immediate to Register move
@@ -281,17 +322,71 @@ impl Nes {
             0x00 => self.raise_interrupt(Interrupt::BREAK),
             //NOP
             0xEA | 0x1A | 0x3A | 0x5A | 0x7A | 0xDA | 0xFA => instruction.cycles,
-            0x80 | 0x82 | 0x89 | 0xC2 | 0xE2 | 0x04 | 0x44 | 0x64 => {
-                self.read_instruction_operand_8bit();
-                3
+            0x80 | 0x82 | 0x89 | 0xC2 | 0xE2 | 0x04 | 0x44 | 0x64 | 0x14 | 0x34 | 0x54 | 0x74
+            | 0xD4 | 0xF4 | 0x0C | 0x1C | 0x3C | 0x5C | 0x7C | 0xDC | 0xFC => self.nop(instruction),
+
+            //Unofficial opcodes
+            //LAX
+            0xA7 | 0xB7 | 0xAF | 0xBF | 0xA3 | 0xB3 => self.lax(instruction),
+            //SAX
+            0x87 | 0x97 | 0x8F | 0x83 => self.st(self.a & self.x, instruction),
+            //DCP
+            0xC7 | 0xD7 | 0xCF | 0xDF | 0xDB | 0xC3 | 0xD3 => self.dcp(instruction),
+            //ISC
+            0xE7 | 0xF7 | 0xEF | 0xFF | 0xFB | 0xE3 | 0xF3 => self.isc(instruction),
+            //SLO
+            0x07 | 0x17 | 0x0F | 0x1F | 0x1B | 0x03 | 0x13 => self.slo(instruction),
+            //RLA
+            0x27 | 0x37 | 0x2F | 0x3F | 0x3B | 0x23 | 0x33 => self.rla(instruction),
+            //SRE
+            0x47 | 0x57 | 0x4F | 0x5F | 0x5B | 0x43 | 0x53 => self.sre(instruction),
+            //RRA
+            0x67 | 0x77 | 0x6F | 0x7F | 0x7B | 0x63 | 0x73 => self.rra(instruction),
+            //ANC
+            0x0B | 0x2B => self.anc(instruction),
+            //ALR
+            0x4B => self.alr(instruction),
+            //ARR
+            0x6B => self.arr(instruction),
+            //AXS
+            0xCB => self.axs(instruction),
+            //ANE
+            0x8B => self.ane(instruction),
+            //LXA
+            0xAB => self.lxa(instruction),
+            //SHA
+            0x93 | 0x9F => self.sh(self.a & self.x, self.y, instruction),
+            //SHY
+            0x9C => self.sh(self.y, self.x, instruction),
+            //SHX
+            0x9E => self.sh(self.x, self.y, instruction),
+            //TAS
+            0x9B => {
+                self.stack_ptr = self.a & self.x;
+                self.sh(self.stack_ptr, self.y, instruction)
             }
-            //Illegal opcodes
-            0x0b | 0x2B | 0x4B | 0x6B | 0xCB | 0xAB => {
-                self.read_instruction_operand_8bit();
-                2
+            //LAS
+            0xBB => self.las(instruction),
+            //JAM
+            0x02 | 0x12 | 0x22 | 0x32 | 0x42 | 0x52 | 0x62 | 0x72 | 0x92 | 0xB2 | 0xD2 | 0xF2 => {
+                self.jam(opcode)
             }
-            _ => todo!(),
+        };
+
+        //Interrupts are polled before the last cycle of the instruction, while CLI, SEI and PLP
+        //change the I flag during that cycle: their effect is delayed by one instruction
+        self.irq_poll_disabled = match opcode {
+            0x58 | 0x78 | 0x28 => irq_disabled_before,
+            _ => (self.flag & IRQ_DISABLE) != 0,
         };
         return cycles;
     }
+
+    pub fn set_irq(&mut self, source: IrqSource) {
+        self.irq_line = self.irq_line | source as u8;
+    }
+
+    pub fn clear_irq(&mut self, source: IrqSource) {
+        self.irq_line = self.irq_line & !(source as u8);
+    }
 }
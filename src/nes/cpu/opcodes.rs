@@ -38,13 +38,13 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x03,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 8,
+        address_mode: AddressMode::IndirectX,
     },
     Instruction {
         opcode: 0x04,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 3,
+        address_mode: AddressMode::ZeroPage,
     },
     Instruction {
         opcode: 0x05,
@@ -58,8 +58,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x07,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 5,
+        address_mode: AddressMode::ZeroPage,
     },
     Instruction {
         opcode: 0x08,
@@ -79,12 +79,12 @@ pub const OPCODES: [Instruction; 256] = [
     Instruction {
         opcode: 0x0B,
         cycles: 2,
-        address_mode: AddressMode::Implied,
+        address_mode: AddressMode::Immediate,
     },
     Instruction {
         opcode: 0x0C,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 4,
+        address_mode: AddressMode::Absolute,
     },
     Instruction {
         opcode: 0x0D,
@@ -98,8 +98,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x0F,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 6,
+        address_mode: AddressMode::Absolute,
     },
     Instruction {
         opcode: 0x10,
@@ -118,13 +118,13 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x13,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 8,
+        address_mode: AddressMode::IndirectY,
     },
     Instruction {
         opcode: 0x14,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 4,
+        address_mode: AddressMode::ZeroPageX,
     },
     Instruction {
         opcode: 0x15,
@@ -138,8 +138,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x17,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 6,
+        address_mode: AddressMode::ZeroPageX,
     },
     Instruction {
         opcode: 0x18,
@@ -158,13 +158,13 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x1B,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 7,
+        address_mode: AddressMode::AbsoluteY,
     },
     Instruction {
         opcode: 0x1C,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 4,
+        address_mode: AddressMode::AbsoluteX,
     },
     Instruction {
         opcode: 0x1D,
@@ -178,8 +178,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x1F,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 7,
+        address_mode: AddressMode::AbsoluteX,
     },
     Instruction {
         opcode: 0x20,
@@ -198,8 +198,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x23,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 8,
+        address_mode: AddressMode::IndirectX,
     },
     Instruction {
         opcode: 0x24,
@@ -218,8 +218,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x27,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 5,
+        address_mode: AddressMode::ZeroPage,
     },
     Instruction {
         opcode: 0x28,
@@ -239,7 +239,7 @@ pub const OPCODES: [Instruction; 256] = [
     Instruction {
         opcode: 0x2B,
         cycles: 2,
-        address_mode: AddressMode::Implied,
+        address_mode: AddressMode::Immediate,
     },
     Instruction {
         opcode: 0x2C,
@@ -258,8 +258,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x2F,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 6,
+        address_mode: AddressMode::Absolute,
     },
     Instruction {
         opcode: 0x30,
@@ -278,13 +278,13 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x33,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 8,
+        address_mode: AddressMode::IndirectY,
     },
     Instruction {
         opcode: 0x34,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 4,
+        address_mode: AddressMode::ZeroPageX,
     },
     Instruction {
         opcode: 0x35,
@@ -298,8 +298,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x37,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 6,
+        address_mode: AddressMode::ZeroPageX,
     },
     Instruction {
         opcode: 0x38,
@@ -318,13 +318,13 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x3B,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 7,
+        address_mode: AddressMode::AbsoluteY,
     },
     Instruction {
         opcode: 0x3C,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 4,
+        address_mode: AddressMode::AbsoluteX,
     },
     Instruction {
         opcode: 0x3D,
@@ -338,8 +338,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x3F,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 7,
+        address_mode: AddressMode::AbsoluteX,
     },
     Instruction {
         opcode: 0x40,
@@ -358,13 +358,13 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x43,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 8,
+        address_mode: AddressMode::IndirectX,
     },
     Instruction {
         opcode: 0x44,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 3,
+        address_mode: AddressMode::ZeroPage,
     },
     Instruction {
         opcode: 0x45,
@@ -378,8 +378,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x47,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 5,
+        address_mode: AddressMode::ZeroPage,
     },
     Instruction {
         opcode: 0x48,
@@ -399,7 +399,7 @@ pub const OPCODES: [Instruction; 256] = [
     Instruction {
         opcode: 0x4B,
         cycles: 2,
-        address_mode: AddressMode::Implied,
+        address_mode: AddressMode::Immediate,
     },
     Instruction {
         opcode: 0x4C,
@@ -418,8 +418,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x4F,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 6,
+        address_mode: AddressMode::Absolute,
     },
     Instruction {
         opcode: 0x50,
@@ -438,13 +438,13 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x53,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 8,
+        address_mode: AddressMode::IndirectY,
     },
     Instruction {
         opcode: 0x54,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 4,
+        address_mode: AddressMode::ZeroPageX,
     },
     Instruction {
         opcode: 0x55,
@@ -458,8 +458,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x57,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 6,
+        address_mode: AddressMode::ZeroPageX,
     },
     Instruction {
         opcode: 0x58,
@@ -478,13 +478,13 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x5B,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 7,
+        address_mode: AddressMode::AbsoluteY,
     },
     Instruction {
         opcode: 0x5C,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 4,
+        address_mode: AddressMode::AbsoluteX,
     },
     Instruction {
         opcode: 0x5D,
@@ -498,8 +498,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x5F,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 7,
+        address_mode: AddressMode::AbsoluteX,
     },
     Instruction {
         opcode: 0x60,
@@ -518,13 +518,13 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x63,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 8,
+        address_mode: AddressMode::IndirectX,
     },
     Instruction {
         opcode: 0x64,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 3,
+        address_mode: AddressMode::ZeroPage,
     },
     Instruction {
         opcode: 0x65,
@@ -538,8 +538,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x67,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 5,
+        address_mode: AddressMode::ZeroPage,
     },
     Instruction {
         opcode: 0x68,
@@ -559,7 +559,7 @@ pub const OPCODES: [Instruction; 256] = [
     Instruction {
         opcode: 0x6B,
         cycles: 2,
-        address_mode: AddressMode::Implied,
+        address_mode: AddressMode::Immediate,
     },
     Instruction {
         opcode: 0x6C,
@@ -578,8 +578,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x6F,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 6,
+        address_mode: AddressMode::Absolute,
     },
     Instruction {
         opcode: 0x70,
@@ -598,13 +598,13 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x73,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 8,
+        address_mode: AddressMode::IndirectY,
     },
     Instruction {
         opcode: 0x74,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 4,
+        address_mode: AddressMode::ZeroPageX,
     },
     Instruction {
         opcode: 0x75,
@@ -618,8 +618,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x77,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 6,
+        address_mode: AddressMode::ZeroPageX,
     },
     Instruction {
         opcode: 0x78,
@@ -638,13 +638,13 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x7B,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 7,
+        address_mode: AddressMode::AbsoluteY,
     },
     Instruction {
         opcode: 0x7C,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 4,
+        address_mode: AddressMode::AbsoluteX,
     },
     Instruction {
         opcode: 0x7D,
@@ -658,13 +658,13 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x7F,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 7,
+        address_mode: AddressMode::AbsoluteX,
     },
     Instruction {
         opcode: 0x80,
         cycles: 2,
-        address_mode: AddressMode::Implied,
+        address_mode: AddressMode::Immediate,
     },
     Instruction {
         opcode: 0x81,
@@ -674,12 +674,12 @@ pub const OPCODES: [Instruction; 256] = [
     Instruction {
         opcode: 0x82,
         cycles: 2,
-        address_mode: AddressMode::Implied,
+        address_mode: AddressMode::Immediate,
     },
     Instruction {
         opcode: 0x83,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 6,
+        address_mode: AddressMode::IndirectX,
     },
     Instruction {
         opcode: 0x84,
@@ -698,8 +698,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x87,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 3,
+        address_mode: AddressMode::ZeroPage,
     },
     Instruction {
         opcode: 0x88,
@@ -709,7 +709,7 @@ pub const OPCODES: [Instruction; 256] = [
     Instruction {
         opcode: 0x89,
         cycles: 2,
-        address_mode: AddressMode::Implied,
+        address_mode: AddressMode::Immediate,
     },
     Instruction {
         opcode: 0x8A,
@@ -719,7 +719,7 @@ pub const OPCODES: [Instruction; 256] = [
     Instruction {
         opcode: 0x8B,
         cycles: 2,
-        address_mode: AddressMode::Implied,
+        address_mode: AddressMode::Immediate,
     },
     Instruction {
         opcode: 0x8C,
@@ -738,8 +738,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x8F,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 4,
+        address_mode: AddressMode::Absolute,
     },
     Instruction {
         opcode: 0x90,
@@ -758,8 +758,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x93,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 6,
+        address_mode: AddressMode::IndirectY,
     },
     Instruction {
         opcode: 0x94,
@@ -778,8 +778,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x97,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 4,
+        address_mode: AddressMode::ZeroPageY,
     },
     Instruction {
         opcode: 0x98,
@@ -798,13 +798,13 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x9B,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 5,
+        address_mode: AddressMode::AbsoluteY,
     },
     Instruction {
         opcode: 0x9C,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 5,
+        address_mode: AddressMode::AbsoluteX,
     },
     Instruction {
         opcode: 0x9D,
@@ -813,13 +813,13 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0x9E,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 5,
+        address_mode: AddressMode::AbsoluteY,
     },
     Instruction {
         opcode: 0x9F,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 5,
+        address_mode: AddressMode::AbsoluteY,
     },
     Instruction {
         opcode: 0xA0,
@@ -838,8 +838,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0xA3,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 6,
+        address_mode: AddressMode::IndirectX,
     },
     Instruction {
         opcode: 0xA4,
@@ -858,8 +858,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0xA7,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 3,
+        address_mode: AddressMode::ZeroPage,
     },
     Instruction {
         opcode: 0xA8,
@@ -879,7 +879,7 @@ pub const OPCODES: [Instruction; 256] = [
     Instruction {
         opcode: 0xAB,
         cycles: 2,
-        address_mode: AddressMode::Implied,
+        address_mode: AddressMode::Immediate,
     },
     Instruction {
         opcode: 0xAC,
@@ -898,8 +898,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0xAF,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 4,
+        address_mode: AddressMode::Absolute,
     },
     Instruction {
         opcode: 0xB0,
@@ -918,8 +918,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0xB3,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 5,
+        address_mode: AddressMode::IndirectY,
     },
     Instruction {
         opcode: 0xB4,
@@ -938,8 +938,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0xB7,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 4,
+        address_mode: AddressMode::ZeroPageY,
     },
     Instruction {
         opcode: 0xB8,
@@ -958,8 +958,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0xBB,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 4,
+        address_mode: AddressMode::AbsoluteY,
     },
     Instruction {
         opcode: 0xBC,
@@ -978,8 +978,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0xBF,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 4,
+        address_mode: AddressMode::AbsoluteY,
     },
     Instruction {
         opcode: 0xC0,
@@ -994,12 +994,12 @@ pub const OPCODES: [Instruction; 256] = [
     Instruction {
         opcode: 0xC2,
         cycles: 2,
-        address_mode: AddressMode::Implied,
+        address_mode: AddressMode::Immediate,
     },
     Instruction {
         opcode: 0xC3,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 8,
+        address_mode: AddressMode::IndirectX,
     },
     Instruction {
         opcode: 0xC4,
@@ -1018,8 +1018,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0xC7,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 5,
+        address_mode: AddressMode::ZeroPage,
     },
     Instruction {
         opcode: 0xC8,
@@ -1039,7 +1039,7 @@ pub const OPCODES: [Instruction; 256] = [
     Instruction {
         opcode: 0xCB,
         cycles: 2,
-        address_mode: AddressMode::Implied,
+        address_mode: AddressMode::Immediate,
     },
     Instruction {
         opcode: 0xCC,
@@ -1058,8 +1058,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0xCF,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 6,
+        address_mode: AddressMode::Absolute,
     },
     Instruction {
         opcode: 0xD0,
@@ -1078,13 +1078,13 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0xD3,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 8,
+        address_mode: AddressMode::IndirectY,
     },
     Instruction {
         opcode: 0xD4,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 4,
+        address_mode: AddressMode::ZeroPageX,
     },
     Instruction {
         opcode: 0xD5,
@@ -1098,8 +1098,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0xD7,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 6,
+        address_mode: AddressMode::ZeroPageX,
     },
     Instruction {
         opcode: 0xD8,
@@ -1118,13 +1118,13 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0xDB,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 7,
+        address_mode: AddressMode::AbsoluteY,
     },
     Instruction {
         opcode: 0xDC,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 4,
+        address_mode: AddressMode::AbsoluteX,
     },
     Instruction {
         opcode: 0xDD,
@@ -1138,8 +1138,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0xDF,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 7,
+        address_mode: AddressMode::AbsoluteX,
     },
     Instruction {
         opcode: 0xE0,
@@ -1154,12 +1154,12 @@ pub const OPCODES: [Instruction; 256] = [
     Instruction {
         opcode: 0xE2,
         cycles: 2,
-        address_mode: AddressMode::Implied,
+        address_mode: AddressMode::Immediate,
     },
     Instruction {
         opcode: 0xE3,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 8,
+        address_mode: AddressMode::IndirectX,
     },
     Instruction {
         opcode: 0xE4,
@@ -1178,8 +1178,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0xE7,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 5,
+        address_mode: AddressMode::ZeroPage,
     },
     Instruction {
         opcode: 0xE8,
@@ -1218,8 +1218,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0xEF,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 6,
+        address_mode: AddressMode::Absolute,
     },
     Instruction {
         opcode: 0xF0,
@@ -1238,13 +1238,13 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0xF3,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 8,
+        address_mode: AddressMode::IndirectY,
     },
     Instruction {
         opcode: 0xF4,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 4,
+        address_mode: AddressMode::ZeroPageX,
     },
     Instruction {
         opcode: 0xF5,
@@ -1258,8 +1258,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0xF7,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 6,
+        address_mode: AddressMode::ZeroPageX,
     },
     Instruction {
         opcode: 0xF8,
@@ -1278,13 +1278,13 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0xFB,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 7,
+        address_mode: AddressMode::AbsoluteY,
     },
     Instruction {
         opcode: 0xFC,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 4,
+        address_mode: AddressMode::AbsoluteX,
     },
     Instruction {
         opcode: 0xFD,
@@ -1298,8 +1298,8 @@ pub const OPCODES: [Instruction; 256] = [
     },
     Instruction {
         opcode: 0xFF,
-        cycles: 2,
-        address_mode: AddressMode::Implied,
+        cycles: 7,
+        address_mode: AddressMode::AbsoluteX,
     },
 ];
 
@@ -1440,17 +1440,13 @@ impl Nes {
         (value, 4)
     }
 
-    pub(super) fn arithmetic_register_a(
-        &mut self,
-        arithmetic_op: fn(a: u8, carry: u8, data: u8) -> u16,
-        instruction: &Instruction,
-    ) -> u32 {
-        let (operand, is_page_cross) = self.get_operand_address(&instruction.address_mode);
+    //ADC core: SBC is the same operation with the value complemented
+    pub(super) fn add_to_register_a(&mut self, value: u8) {
         let carry = if (self.flag & CARRY) != 0 { 1 } else { 0 };
 
-        let value = self.read_byte(operand);
-
-        let result_16bit = arithmetic_op(self.a, carry, value);
+        let result_16bit = u16::from(self.a)
+            .wrapping_add(carry)
+            .wrapping_add(u16::from(value));
         let result_8bit = result_16bit as u8;
 
         //Handle flags
@@ -1470,6 +1466,12 @@ impl Nes {
 
         //Update result
         self.a = result_8bit;
+    }
+
+    pub(super) fn adc(&mut self, instruction: &Instruction) -> u32 {
+        let (operand, is_page_cross) = self.get_operand_address(&instruction.address_mode);
+        let value = self.read_byte(operand);
+        self.add_to_register_a(value);
         return if is_page_cross {
             instruction.cycles + 1
         } else {
@@ -1477,45 +1479,10 @@ impl Nes {
         };
     }
 
-    pub(super) fn add(a: u8, carry: u8, data: u8) -> u16 {
-        u16::from(a)
-            .wrapping_add(u16::from(carry))
-            .wrapping_add(u16::from(data))
-    }
-
-    pub(super) fn adc(&mut self, instruction: &Instruction) -> u32 {
-        return self.arithmetic_register_a(Nes::add, instruction);
-    }
-
-    //TODO refactor
     pub(super) fn sbc(&mut self, instruction: &Instruction) -> u32 {
         let (operand, is_page_cross) = self.get_operand_address(&instruction.address_mode);
-        let carry: u8 = if (self.flag & CARRY) != 0 { 1 } else { 0 };
-
-        let value = self.read_byte(operand) ^ 0xFF;
-
-        let result_16bit = u16::from(self.a)
-            .wrapping_add(u16::from(carry))
-            .wrapping_add(u16::from(value));
-        let result_8bit = result_16bit as u8;
-
-        //Handle flags
-        if result_16bit > 0xff {
-            self.flag = self.flag | CARRY;
-        } else {
-            self.flag = self.flag & !CARRY;
-        }
-        //If the two sign bits from a and value are the same but result is different we have overflow
-        if (self.a ^ result_8bit) & (value ^ result_8bit) & 0x80 != 0 {
-            self.flag = self.flag | OVERFLOW;
-        } else {
-            self.flag = self.flag & !OVERFLOW;
-        }
-
-        self.update_zero_and_negative_flags(result_8bit);
-
-        //Update result
-        self.a = result_8bit;
+        let value = self.read_byte(operand);
+        self.add_to_register_a(value ^ 0xFF);
         return if is_page_cross {
             instruction.cycles + 1
         } else {
@@ -1560,6 +1527,15 @@ impl Nes {
         let (operand, is_page_cross) = self.get_operand_address(&instruction.address_mode);
         let value = self.read_byte(operand);
 
+        self.compare_values(compare_with, value);
+        return if is_page_cross {
+            instruction.cycles + 1
+        } else {
+            instruction.cycles
+        };
+    }
+
+    pub(super) fn compare_values(&mut self, compare_with: u8, value: u8) {
         let result = compare_with.wrapping_sub(value);
         if compare_with >= value {
             self.flag = self.flag | CARRY;
@@ -1568,11 +1544,6 @@ impl Nes {
         }
 
         self.update_zero_and_negative_flags(result);
-        return if is_page_cross {
-            instruction.cycles + 1
-        } else {
-            instruction.cycles
-        };
     }
 
     pub(super) fn bit(&mut self, instruction: &Instruction) -> u32 {
@@ -1615,25 +1586,14 @@ impl Nes {
         let (operand, _) = self.get_operand_address(&instruction.address_mode);
         let value = self.read_byte(operand);
 
-        let rot_value = value << 1;
-        if (value & 0x80) != 0 {
-            self.flag = self.flag | CARRY;
-        } else {
-            self.flag = self.flag & !CARRY;
-        }
+        let rot_value = self.shift_left(value);
         self.write_byte(operand, rot_value);
         self.update_zero_and_negative_flags(rot_value);
         return instruction.cycles;
     }
 
     pub(super) fn shl_acc(&mut self) -> u32 {
-        let rot_value = self.a << 1;
-        if (self.a & 0x80) != 0 {
-            self.flag = self.flag | CARRY;
-        } else {
-            self.flag = self.flag & !CARRY;
-        }
-        self.a = rot_value;
+        self.a = self.shift_left(self.a);
         self.update_zero_and_negative_flags(self.a);
         return 2;
     }
@@ -1642,25 +1602,14 @@ impl Nes {
         let (operand, _) = self.get_operand_address(&instruction.address_mode);
         let value = self.read_byte(operand);
 
-        let rot_value = value >> 1;
-        if (value & 0x01) != 0 {
-            self.flag = self.flag | CARRY;
-        } else {
-            self.flag = self.flag & !CARRY;
-        }
+        let rot_value = self.shift_right(value);
         self.write_byte(operand, rot_value);
         self.update_zero_and_negative_flags(rot_value);
         return instruction.cycles;
     }
 
     pub(super) fn shr_acc(&mut self) -> u32 {
-        let rot_value = self.a >> 1;
-        if (self.a & 0x01) != 0 {
-            self.flag = self.flag | CARRY;
-        } else {
-            self.flag = self.flag & !CARRY;
-        }
-        self.a = rot_value;
+        self.a = self.shift_right(self.a);
         self.update_zero_and_negative_flags(self.a);
         return 2;
     }
@@ -1668,37 +1617,15 @@ impl Nes {
     pub(super) fn rol(&mut self, instruction: &Instruction) -> u32 {
         let (operand, _) = self.get_operand_address(&instruction.address_mode);
         let value = self.read_byte(operand);
-        let old_carry: u8 = if (self.flag & CARRY) != 0 { 1 } else { 0 };
-
-        let mut rot_value = value << 1;
-        if (value & 0x80) != 0 {
-            self.flag = self.flag | CARRY;
-        } else {
-            self.flag = self.flag & !CARRY;
-        }
-        if old_carry == 1 {
-            rot_value = rot_value | 0x01
-        }
 
+        let rot_value = self.rotate_left(value);
         self.write_byte(operand, rot_value);
         self.update_zero_and_negative_flags(rot_value);
         return instruction.cycles;
     }
 
     pub(super) fn rol_acc(&mut self) -> u32 {
-        let old_carry: u8 = if (self.flag & CARRY) != 0 { 1 } else { 0 };
-
-        let mut rot_value = self.a << 1;
-        if (self.a & 0x80) != 0 {
-            self.flag = self.flag | CARRY;
-        } else {
-            self.flag = self.flag & !CARRY;
-        }
-        if old_carry == 1 {
-            rot_value = rot_value | 0x01
-        }
-
-        self.a = rot_value;
+        self.a = self.rotate_left(self.a);
         self.update_zero_and_negative_flags(self.a);
         return 2;
     }
@@ -1706,39 +1633,56 @@ impl Nes {
     pub(super) fn ror(&mut self, instruction: &Instruction) -> u32 {
         let (operand, _) = self.get_operand_address(&instruction.address_mode);
         let value = self.read_byte(operand);
-        let old_carry: u8 = if (self.flag & CARRY) != 0 { 1 } else { 0 };
-
-        let mut rot_value = value >> 1;
-        if (value & 0x01) != 0 {
-            self.flag = self.flag | CARRY;
-        } else {
-            self.flag = self.flag & !CARRY;
-        }
-        if old_carry == 1 {
-            rot_value = rot_value | 0x80
-        }
 
+        let rot_value = self.rotate_right(value);
         self.write_byte(operand, rot_value);
         self.update_zero_and_negative_flags(rot_value);
         return instruction.cycles;
     }
 
     pub(super) fn ror_acc(&mut self) -> u32 {
-        let old_carry = (self.flag & CARRY) != 0;
+        self.a = self.rotate_right(self.a);
+        self.update_zero_and_negative_flags(self.a);
+        return 2;
+    }
 
-        let mut rot_value = self.a >> 1;
-        if (self.a & 0x01) != 0 {
-            self.flag = self.flag | CARRY;
+    //Shift and rotate helpers: they only update the carry, the other flags depend on the instruction
+    pub(super) fn shift_left(&mut self, value: u8) -> u8 {
+        self.set_carry((value & 0x80) != 0);
+        value << 1
+    }
+
+    pub(super) fn shift_right(&mut self, value: u8) -> u8 {
+        self.set_carry((value & 0x01) != 0);
+        value >> 1
+    }
+
+    pub(super) fn rotate_left(&mut self, value: u8) -> u8 {
+        let old_carry = (self.flag & CARRY) != 0;
+        let rot_value = self.shift_left(value);
+        if old_carry {
+            rot_value | 0x01
         } else {
-            self.flag = self.flag & !CARRY;
+            rot_value
         }
+    }
+
+    pub(super) fn rotate_right(&mut self, value: u8) -> u8 {
+        let old_carry = (self.flag & CARRY) != 0;
+        let rot_value = self.shift_right(value);
         if old_carry {
-            rot_value = rot_value | 0x80
+            rot_value | 0x80
+        } else {
+            rot_value
         }
+    }
 
-        self.a = rot_value;
-        self.update_zero_and_negative_flags(self.a);
-        return 2;
+    pub(super) fn set_carry(&mut self, is_set: bool) {
+        if is_set {
+            self.flag = self.flag | CARRY;
+        } else {
+            self.flag = self.flag & !CARRY;
+        }
     }
 
     pub(super) fn conditional_jump(&mut self, instruction: &Instruction, condition: bool) -> u32 {
@@ -1759,7 +1703,7 @@ impl Nes {
 
     pub(in crate::nes) fn raise_interrupt(&mut self, interrupt_type: Interrupt) -> u32 {
         let break_flag = match interrupt_type {
-            Interrupt::NMI => 0,
+            Interrupt::NMI | Interrupt::IRQ => 0,
             Interrupt::BREAK => 1,
         };
         if break_flag != 0 {
@@ -1774,9 +1718,10 @@ impl Nes {
 
         self.push(self.flag);
         self.flag = self.flag | IRQ_DISABLE;
+        self.irq_poll_disabled = true;
 
         let interrupt_routine = match interrupt_type {
-            Interrupt::BREAK => 0xFFFE,
+            Interrupt::BREAK | Interrupt::IRQ => 0xFFFE,
             Interrupt::NMI => 0xFFFA,
         };
         let interrupt_fn = self.read_word(interrupt_routine);
@@ -1790,6 +1735,10 @@ impl Nes {
         self.y = 0;
         self.stack_ptr = 0xFD;
         self.flag = 0b100100;
+        self.cpu_jam = None;
+        self.irq_poll_disabled = true;
+        //Reset silences the APU, like a write of 0 in $4015
+        self.apu.reset();
         self.prog_counter = self.read_word(0xFFFC);
     }
 }
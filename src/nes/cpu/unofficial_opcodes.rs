@@ -0,0 +1,204 @@
+use crate::memory::Memory;
+use crate::nes::cpu::opcodes::Instruction;
+use crate::nes::{CpuJam, Nes, CARRY, OVERFLOW};
+
+//ANE and LXA mix A with a value that depends on the chip (and on temperature...).
+//0xEE is what most 2A03 have been measured with
+const UNSTABLE_MAGIC: u8 = 0xEE;
+
+/*
+Unofficial opcodes of the 2A03. Most of them are two official instructions executed
+together, sharing the addressing mode of the official ones in the same column.
+ */
+impl Nes {
+    //NOPs with an operand still read it (reads can have side effects on I/O registers)
+    pub(super) fn nop(&mut self, instruction: &Instruction) -> u32 {
+        let (operand, is_page_cross) = self.get_operand_address(&instruction.address_mode);
+        self.read_byte(operand);
+        return if is_page_cross {
+            instruction.cycles + 1
+        } else {
+            instruction.cycles
+        };
+    }
+
+    //LAX = LDA + LDX
+    pub(super) fn lax(&mut self, instruction: &Instruction) -> u32 {
+        let (operand, is_page_cross) = self.get_operand_address(&instruction.address_mode);
+        let value = self.read_byte(operand);
+        self.a = value;
+        self.x = value;
+        self.update_zero_and_negative_flags(value);
+        return if is_page_cross {
+            instruction.cycles + 1
+        } else {
+            instruction.cycles
+        };
+    }
+
+    //DCP = DEC + CMP
+    pub(super) fn dcp(&mut self, instruction: &Instruction) -> u32 {
+        let (operand, _) = self.get_operand_address(&instruction.address_mode);
+        let value = self.read_byte(operand).wrapping_sub(1);
+        self.write_byte(operand, value);
+        self.compare_values(self.a, value);
+        return instruction.cycles;
+    }
+
+    //ISC = INC + SBC
+    pub(super) fn isc(&mut self, instruction: &Instruction) -> u32 {
+        let (operand, _) = self.get_operand_address(&instruction.address_mode);
+        let value = self.read_byte(operand).wrapping_add(1);
+        self.write_byte(operand, value);
+        self.add_to_register_a(value ^ 0xFF);
+        return instruction.cycles;
+    }
+
+    //SLO = ASL + ORA
+    pub(super) fn slo(&mut self, instruction: &Instruction) -> u32 {
+        let (operand, _) = self.get_operand_address(&instruction.address_mode);
+        let value = self.read_byte(operand);
+        let rot_value = self.shift_left(value);
+        self.write_byte(operand, rot_value);
+        self.a = self.a | rot_value;
+        self.update_zero_and_negative_flags(self.a);
+        return instruction.cycles;
+    }
+
+    //RLA = ROL + AND
+    pub(super) fn rla(&mut self, instruction: &Instruction) -> u32 {
+        let (operand, _) = self.get_operand_address(&instruction.address_mode);
+        let value = self.read_byte(operand);
+        let rot_value = self.rotate_left(value);
+        self.write_byte(operand, rot_value);
+        self.a = self.a & rot_value;
+        self.update_zero_and_negative_flags(self.a);
+        return instruction.cycles;
+    }
+
+    //SRE = LSR + EOR
+    pub(super) fn sre(&mut self, instruction: &Instruction) -> u32 {
+        let (operand, _) = self.get_operand_address(&instruction.address_mode);
+        let value = self.read_byte(operand);
+        let rot_value = self.shift_right(value);
+        self.write_byte(operand, rot_value);
+        self.a = self.a ^ rot_value;
+        self.update_zero_and_negative_flags(self.a);
+        return instruction.cycles;
+    }
+
+    //RRA = ROR + ADC, the carry out of ROR is used by ADC
+    pub(super) fn rra(&mut self, instruction: &Instruction) -> u32 {
+        let (operand, _) = self.get_operand_address(&instruction.address_mode);
+        let value = self.read_byte(operand);
+        let rot_value = self.rotate_right(value);
+        self.write_byte(operand, rot_value);
+        self.add_to_register_a(rot_value);
+        return instruction.cycles;
+    }
+
+    //ANC = AND, then bit 7 is copied in the carry
+    pub(super) fn anc(&mut self, instruction: &Instruction) -> u32 {
+        let (operand, _) = self.get_operand_address(&instruction.address_mode);
+        self.a = self.a & self.read_byte(operand);
+        self.update_zero_and_negative_flags(self.a);
+        self.set_carry((self.a & 0x80) != 0);
+        return instruction.cycles;
+    }
+
+    //ALR = AND + LSR A
+    pub(super) fn alr(&mut self, instruction: &Instruction) -> u32 {
+        let (operand, _) = self.get_operand_address(&instruction.address_mode);
+        let value = self.a & self.read_byte(operand);
+        self.a = self.shift_right(value);
+        self.update_zero_and_negative_flags(self.a);
+        return instruction.cycles;
+    }
+
+    //ARR = AND + ROR A, but C and V come from bits 6 and 5 of the result
+    pub(super) fn arr(&mut self, instruction: &Instruction) -> u32 {
+        let (operand, _) = self.get_operand_address(&instruction.address_mode);
+        let value = self.a & self.read_byte(operand);
+        let carry = if (self.flag & CARRY) != 0 { 0x80 } else { 0 };
+        self.a = (value >> 1) | carry;
+        self.update_zero_and_negative_flags(self.a);
+
+        let bit6 = (self.a >> 6) & 0x1;
+        let bit5 = (self.a >> 5) & 0x1;
+        self.set_carry(bit6 != 0);
+        if (bit6 ^ bit5) != 0 {
+            self.flag = self.flag | OVERFLOW;
+        } else {
+            self.flag = self.flag & !OVERFLOW;
+        }
+        return instruction.cycles;
+    }
+
+    //AXS = X is set to (A AND X) - value, flags are updated like CMP
+    pub(super) fn axs(&mut self, instruction: &Instruction) -> u32 {
+        let (operand, _) = self.get_operand_address(&instruction.address_mode);
+        let value = self.read_byte(operand);
+        let a_and_x = self.a & self.x;
+        self.compare_values(a_and_x, value);
+        self.x = a_and_x.wrapping_sub(value);
+        return instruction.cycles;
+    }
+
+    //ANE (XAA): A = (A OR magic) AND X AND value
+    pub(super) fn ane(&mut self, instruction: &Instruction) -> u32 {
+        let (operand, _) = self.get_operand_address(&instruction.address_mode);
+        let value = self.read_byte(operand);
+        self.a = (self.a | UNSTABLE_MAGIC) & self.x & value;
+        self.update_zero_and_negative_flags(self.a);
+        return instruction.cycles;
+    }
+
+    //LXA (LAX immediate): A = X = (A OR magic) AND value
+    pub(super) fn lxa(&mut self, instruction: &Instruction) -> u32 {
+        let (operand, _) = self.get_operand_address(&instruction.address_mode);
+        let value = self.read_byte(operand);
+        self.a = (self.a | UNSTABLE_MAGIC) & value;
+        self.x = self.a;
+        self.update_zero_and_negative_flags(self.a);
+        return instruction.cycles;
+    }
+
+    //SHA, SHX, SHY and TAS store value AND (high byte of the base address + 1).
+    //If the indexing crosses a page, the high byte of the target address is corrupted too
+    pub(super) fn sh(&mut self, value: u8, index: u8, instruction: &Instruction) -> u32 {
+        let (operand, is_page_cross) = self.get_operand_address(&instruction.address_mode);
+        let base_high_byte = (operand.wrapping_sub(u16::from(index)) >> 8) as u8;
+        let result = value & base_high_byte.wrapping_add(1);
+
+        let address = if is_page_cross {
+            (u16::from(result) << 8) | (operand & 0xFF)
+        } else {
+            operand
+        };
+        self.write_byte(address, result);
+        return instruction.cycles;
+    }
+
+    //LAS: A = X = S = value AND S
+    pub(super) fn las(&mut self, instruction: &Instruction) -> u32 {
+        let (operand, is_page_cross) = self.get_operand_address(&instruction.address_mode);
+        let value = self.read_byte(operand) & self.stack_ptr;
+        self.a = value;
+        self.x = value;
+        self.stack_ptr = value;
+        self.update_zero_and_negative_flags(value);
+        return if is_page_cross {
+            instruction.cycles + 1
+        } else {
+            instruction.cycles
+        };
+    }
+
+    //JAM (KIL): the CPU stops fetching instructions until reset
+    pub(super) fn jam(&mut self, opcode: u8) -> u32 {
+        let address = self.prog_counter.wrapping_sub(1);
+        self.prog_counter = address;
+        self.cpu_jam = Some(CpuJam { opcode, address });
+        return 2;
+    }
+}
@@ -1,14 +1,36 @@
+use crate::cartridge::header::CartridgeTiming;
 use crate::cartridge::Cartridge;
+use crate::nes::apu::Apu;
+use crate::nes::nsf::NsfPlayer;
+use crate::nes::ppu::{SpritePixel, TRANSPARENT_SPRITE_PIXEL};
 
+mod apu;
 mod cpu;
+mod nsf;
 mod ppu;
 mod system_bus;
 
 enum Interrupt {
     NMI,
+    IRQ,
     BREAK,
 }
 
+//Devices sharing the IRQ line of the CPU. The line stays asserted while at least one of them holds it
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum IrqSource {
+    Mapper = 0b001,
+    FrameCounter = 0b010,
+    Dmc = 0b100,
+}
+
+//Opcode and address of a JAM instruction that halted the CPU
+#[derive(Copy, Clone, Debug)]
+pub struct CpuJam {
+    pub opcode: u8,
+    pub address: u16,
+}
+
 pub const CARRY: u8 = 0b00000001;
 pub const ZERO: u8 = 0b00000010;
 pub const IRQ_DISABLE: u8 = 0b00000100;
@@ -33,6 +55,11 @@ pub struct Nes {
     flag: u8,
     //Program Counter
     prog_counter: u16,
+    cpu_jam: Option<CpuJam>,
+    //One bit per IrqSource
+    irq_line: u8,
+    //I flag as seen by the interrupt polling of the last instruction
+    irq_poll_disabled: bool,
 
     //Main WRAM
     cpu_memory: [u8; 0x800],
@@ -41,11 +68,29 @@ pub struct Nes {
     ppuctrl: u8,
     ppumask: u8,
     ppustatus: u8,
+    //PAL and Dendy PPUs emphasize green with bit 5 of PPUMASK and red with bit 6
+    swap_red_green_emphasis: bool,
     oam_addr: u8,
     oam_ram: [u8; 0x100],
 
     ppu_second_write: bool,
 
+    //NMI line of the PPU and edge detector of the CPU
+    nmi_output: bool,
+    nmi_pending: bool,
+    nmi_delayed: bool,
+    //PPUSTATUS read one dot before VBlank
+    suppress_vblank: bool,
+
+    //Bus accesses of the current instruction, and CPU cycles already run by the PPU and APU
+    cpu_bus_accesses: u32,
+    ppu_synced_cycles: u32,
+    apu_synced_cycles: u32,
+    //CPU cycles stolen by the DMC memory reader, charged to the next instruction
+    dmc_stall_cycles: u32,
+    //Bus access of the current instruction reading the controller port
+    controller_read_cycle: Option<u32>,
+
     vram_data: u8,
 
     ppu_memory: [u8; 0x800],
@@ -53,13 +98,16 @@ pub struct Nes {
     request_dma: bool,
     dma_src: u16,
 
+    //Line (0 - 261) and dot (0 - 340) that the PPU runs next
     current_scanline: u32,
     clock_current_scanline: u32,
+    odd_frame: bool,
+    //Incremented at the start of every VBlank
+    frame_count: u64,
 
     palettes: [u8; 0x20],
 
     pub screen: [u32; 256 * 240],
-    background_hit_flag: [bool; 256 * 240],
 
     controller_first_port: [bool; 8],
     first_port_strobing: bool,
@@ -68,8 +116,64 @@ pub struct Nes {
     ppu_v: u16,
     ppu_t: u16,
     ppu_x: u8,
+
+    //Background pipeline: latches of the tile being fetched and shift registers
+    bg_next_tile_index: u8,
+    bg_next_attribute: u8,
+    bg_next_pattern_low: u8,
+    bg_next_pattern_high: u8,
+    bg_pattern_shift_low: u16,
+    bg_pattern_shift_high: u16,
+    bg_attribute_shift_low: u16,
+    bg_attribute_shift_high: u16,
+
+    //Sprites found by the evaluation (4 bytes each), and pixels of the sprites for the current line
+    secondary_oam: [u8; 32],
+    sprite_count: usize,
+    sprite_zero_in_line: bool,
+    sprite_eval_index: usize,
+    sprite_eval_byte: usize,
+    sprite_pattern_low: u8,
+    sprite_line: [SpritePixel; 256],
+
+    apu: Apu,
+    //Set when playing a NSF file instead of a game
+    nsf_player: Option<NsfPlayer>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ApuChannel {
+    Pulse1 = 0,
+    Pulse2,
+    Triangle,
+    Noise,
+    Dmc,
+    //Audio generated by the cartridge
+    Expansion,
+}
+
+impl ApuChannel {
+    pub fn get_name(&self) -> &'static str {
+        return match self {
+            ApuChannel::Pulse1 => "pulse1",
+            ApuChannel::Pulse2 => "pulse2",
+            ApuChannel::Triangle => "triangle",
+            ApuChannel::Noise => "noise",
+            ApuChannel::Dmc => "dmc",
+            ApuChannel::Expansion => "expansion",
+        };
+    }
 }
 
+//Channels of the 2A03
+pub const APU_CHANNELS: [ApuChannel; 5] = [
+    ApuChannel::Pulse1,
+    ApuChannel::Pulse2,
+    ApuChannel::Triangle,
+    ApuChannel::Noise,
+    ApuChannel::Dmc,
+];
+
 pub enum NesControllerButton {
     A = 0,
     B,
@@ -83,6 +187,10 @@ pub enum NesControllerButton {
 
 impl Nes {
     pub fn create_nes(cartridge: Box<dyn Cartridge>) -> Nes {
+        let swap_red_green_emphasis = matches!(
+            cartridge.get_header().timing,
+            CartridgeTiming::PAL | CartridgeTiming::Dendy
+        );
         Nes {
             a: 0,
             x: 0,
@@ -90,6 +198,9 @@ impl Nes {
             stack_ptr: 0,
             flag: 0,
             prog_counter: 0,
+            cpu_jam: None,
+            irq_line: 0,
+            irq_poll_disabled: true,
 
             //Main WRAM
             cpu_memory: [0x0; 0x800],
@@ -98,11 +209,23 @@ impl Nes {
             ppuctrl: 0,
             ppumask: 0,
             ppustatus: 0,
+            swap_red_green_emphasis,
             oam_addr: 0,
             oam_ram: [0x0; 0x100],
 
             ppu_second_write: false,
 
+            nmi_output: false,
+            nmi_pending: false,
+            nmi_delayed: false,
+            suppress_vblank: false,
+
+            cpu_bus_accesses: 0,
+            ppu_synced_cycles: 0,
+            apu_synced_cycles: 0,
+            dmc_stall_cycles: 0,
+            controller_read_cycle: None,
+
             vram_data: 0x0,
             ppu_memory: [0x0; 0x800],
 
@@ -111,10 +234,11 @@ impl Nes {
 
             current_scanline: 0,
             clock_current_scanline: 0,
+            odd_frame: false,
+            frame_count: 0,
 
             palettes: [0x0; 0x20],
             screen: [0x0; 256 * 240],
-            background_hit_flag: [false; 256 * 240],
             controller_first_port: [false; 8],
             first_port_strobing: false,
             first_port_strobing_index: 0,
@@ -122,6 +246,50 @@ impl Nes {
             ppu_v: 0,
             ppu_t: 0,
             ppu_x: 0,
+
+            bg_next_tile_index: 0,
+            bg_next_attribute: 0,
+            bg_next_pattern_low: 0,
+            bg_next_pattern_high: 0,
+            bg_pattern_shift_low: 0,
+            bg_pattern_shift_high: 0,
+            bg_attribute_shift_low: 0,
+            bg_attribute_shift_high: 0,
+
+            secondary_oam: [0xFF; 32],
+            sprite_count: 0,
+            sprite_zero_in_line: false,
+            sprite_eval_index: 0,
+            sprite_eval_byte: 0,
+            sprite_pattern_low: 0,
+            sprite_line: [TRANSPARENT_SPRITE_PIXEL; 256],
+
+            apu: Apu::new(),
+            nsf_player: None,
         }
     }
+
+    pub fn get_frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    pub fn get_cpu_jam(&self) -> Option<CpuJam> {
+        self.cpu_jam
+    }
+
+    pub fn export_battery_ram(&mut self) -> Option<Vec<u8>> {
+        self.cartridge.get_battery_ram().map(|ram| ram.to_vec())
+    }
+
+    //Returns false if the cartridge has no battery backed memory
+    pub fn import_battery_ram(&mut self, data: &[u8]) -> bool {
+        return match self.cartridge.get_battery_ram() {
+            Some(ram) => {
+                let len = ram.len().min(data.len());
+                ram[..len].copy_from_slice(&data[..len]);
+                true
+            }
+            None => false,
+        };
+    }
 }
@@ -0,0 +1,130 @@
+use crate::cartridge::nsf::NsfInfo;
+use crate::nes::{Nes, IRQ_DISABLE, UNUSED};
+
+//INIT and PLAY return to this address, where the CPU idles until the next call of PLAY
+const NSF_IDLE_ADDRESS: u16 = 0x4100;
+//The console is always emulated at NTSC speed
+const NSF_CPU_FREQUENCY: u64 = 1789773;
+//Speed used when the file doesn't give one (60 Hz)
+const NSF_DEFAULT_SPEED: u16 = 16639;
+
+//Replaces the reset vector and the NMI of a game: calls INIT for a track, then PLAY on a timer
+pub(crate) struct NsfPlayer {
+    init_address: u16,
+    play_address: u16,
+    bankswitch: Option<[u8; 8]>,
+    is_pal: bool,
+
+    //CPU cycles between two calls of PLAY
+    play_period: u32,
+    cycles_until_play: u32,
+    play_pending: bool,
+}
+
+impl NsfPlayer {
+    fn clock(&mut self, cpu_cycles: u32) {
+        if cpu_cycles >= self.cycles_until_play {
+            //A late call of PLAY doesn't delay the next ones
+            self.cycles_until_play = (self.cycles_until_play + self.play_period)
+                .saturating_sub(cpu_cycles)
+                .max(1);
+            self.play_pending = true;
+        } else {
+            self.cycles_until_play -= cpu_cycles;
+        }
+    }
+}
+
+impl Nes {
+    //Turns the console into a NSF player, play_nsf_track starts the music
+    pub fn load_nsf(&mut self, info: &NsfInfo) {
+        let speed = match info.get_play_speed() {
+            0 => NSF_DEFAULT_SPEED,
+            speed => speed,
+        };
+        let play_period = (u64::from(speed) * NSF_CPU_FREQUENCY / 1_000_000) as u32;
+
+        self.nsf_player = Some(NsfPlayer {
+            init_address: info.init_address,
+            play_address: info.play_address,
+            bankswitch: info.bankswitch,
+            is_pal: info.is_pal,
+            play_period,
+            cycles_until_play: play_period,
+            play_pending: false,
+        });
+    }
+
+    //track is 0-based. Does nothing if no NSF has been loaded
+    pub fn play_nsf_track(&mut self, track: u8) {
+        let (init_address, bankswitch, is_pal) = match self.nsf_player.as_mut() {
+            Some(player) => {
+                player.cycles_until_play = player.play_period;
+                player.play_pending = false;
+                (player.init_address, player.bankswitch, player.is_pal)
+            }
+            None => return,
+        };
+
+        //Clean state expected by INIT: empty RAM, silent APU, frame IRQ disabled
+        self.cpu_memory = [0; 0x800];
+        for addr in 0..0x2000 {
+            self.cartridge.write_ram_byte(addr, 0);
+        }
+        for addr in 0x4000..=0x4013 {
+            self.apu.write_register(addr, 0);
+        }
+        self.apu.write_status(0);
+        self.apu.write_status(0x0F);
+        self.apu.write_frame_counter(0x40);
+        if let Some(banks) = bankswitch {
+            for (i, bank) in banks.iter().enumerate() {
+                self.cartridge
+                    .write_expansion_byte(0x5FF8 + i as u16, *bank);
+            }
+        }
+
+        self.a = track;
+        self.x = if is_pal { 1 } else { 0 };
+        self.y = 0;
+        self.flag = IRQ_DISABLE | UNUSED;
+        self.stack_ptr = 0xFD;
+        self.cpu_jam = None;
+        self.irq_poll_disabled = true;
+        self.nmi_pending = false;
+        self.call_nsf_routine(init_address);
+    }
+
+    //Like a JSR from NSF_IDLE_ADDRESS
+    fn call_nsf_routine(&mut self, addr: u16) {
+        let return_address = NSF_IDLE_ADDRESS.wrapping_sub(1);
+        for value in [(return_address >> 8) as u8, return_address as u8] {
+            self.cpu_memory[0x100 + self.stack_ptr as usize] = value;
+            self.stack_ptr = self.stack_ptr.wrapping_sub(1);
+        }
+        self.prog_counter = addr;
+    }
+
+    //Returns the CPU cycles spent instead of running an instruction, if the CPU is
+    //waiting at NSF_IDLE_ADDRESS
+    pub(crate) fn step_nsf_player(&mut self) -> Option<u32> {
+        if self.prog_counter != NSF_IDLE_ADDRESS {
+            return None;
+        }
+        let player = self.nsf_player.as_mut()?;
+        if player.play_pending {
+            player.play_pending = false;
+            let play_address = player.play_address;
+            self.call_nsf_routine(play_address);
+            //Same timing as JSR
+            return Some(6);
+        }
+        Some(1)
+    }
+
+    pub(crate) fn clock_nsf_player(&mut self, cpu_cycles: u32) {
+        if let Some(player) = self.nsf_player.as_mut() {
+            player.clock(cpu_cycles);
+        }
+    }
+}
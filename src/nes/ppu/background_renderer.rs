@@ -1,65 +1,130 @@
+use crate::nes::ppu::registers::{
+    BACKGROUD_LEFT_ENABLED, BACKGROUND_ENABLED, BACKGROUND_PATTERN_TABLE,
+};
 use crate::Nes;
 
 impl Nes {
-    pub(super) fn render_background(&mut self, current_scanline: u16) {
-        let mut current_pixel = 0;
-
-        for row_tile in 0..=32 {
-            let tile_address = 0x2000 | (self.ppu_v & 0x0FFF);
-            let fine_y = (self.ppu_v >> 12) & 0x7;
-            let fine_x = self.ppu_x;
-
-            let tile_index = self.read_ppu_byte(tile_address);
-            let (tile_first_plane, tile_second_plane) =
-                self.retrieve_tile_row(tile_index, fine_y as u8);
-
-            let palette_msb = self.retrieve_attribute_table_value(tile_address);
-
-            let pixels = Nes::get_tile_row_pixels(palette_msb, tile_first_plane, tile_second_plane);
-
-            let pixels_to_draw = match row_tile {
-                //First tile: I only need the pixels from tile_x_offset
-                0 => fine_x..=7,
-                //Middle tiles: I have to render the entire row
-                1..=31 => 0..=7,
-                //Last tile: I only need to render the tile_x_offset bits
-                32 => {
-                    if fine_x > 0 {
-                        0..=fine_x - 1
-                    } else {
-                        1..=0
-                    }
+    /*
+    Background pipeline, run on every dot of the visible and pre-render lines while rendering:
+    - dots 1-256 and 321-336: every 8 dots, fetch nametable byte, attribute byte, pattern low
+      and pattern high of the next tile, then increment coarse X
+    - dot 256: increment Y
+    - dot 257: copy the horizontal bits of t into v
+    - dots 280-304 (pre-render line only): copy the vertical bits of t into v
+    - dots 337 and 339: two unused nametable fetches
+    The shift registers move by one pixel per dot, and are reloaded every 8 dots
+     */
+    pub(super) fn run_background_dot(&mut self, dot: u32, pre_render_line: bool) {
+        if (2..=257).contains(&dot) || (322..=337).contains(&dot) {
+            self.shift_background_registers();
+        }
+
+        if (1..=256).contains(&dot) || (321..=337).contains(&dot) {
+            match (dot - 1) % 8 {
+                0 => {
+                    self.load_background_registers();
+                    self.bg_next_tile_index = self.read_ppu_byte(0x2000 | (self.ppu_v & 0x0FFF));
+                }
+                2 => {
+                    self.bg_next_attribute =
+                        self.retrieve_attribute_table_value(0x2000 | (self.ppu_v & 0x0FFF));
+                }
+                4 => {
+                    let address = self.get_background_tile_address();
+                    self.bg_next_pattern_low = self.read_ppu_byte(address);
                 }
-                //This should never happens...
-                _ => {
-                    panic!("Too many tiles in this row")
+                6 => {
+                    let address = self.get_background_tile_address();
+                    self.bg_next_pattern_high = self.read_ppu_byte(address.wrapping_add(8));
                 }
-            };
-
-            for i in pixels_to_draw {
-                //If using palette 0 the pixel is transparent -> use default color
-                //TODO maybe this part should be done by
-                let palette_address = if pixels[i as usize] & 0x3 == 0 {
-                    0x3F00
-                } else {
-                    0x3F00 + u16::from(pixels[i as usize])
-                };
-                self.render_pixel(palette_address, current_pixel, current_scanline as u8);
-                self.background_hit_flag
-                    [current_pixel as usize + (current_scanline as usize * 256)] =
-                    palette_address != 0x3F00;
-                current_pixel = current_pixel.wrapping_add(1);
+                7 => self.increment_coarse_x(),
+                _ => {}
             }
+        }
 
-            if (self.ppu_v & 0x001F) == 31 {
-                self.ppu_v &= !0x001F;
-                self.ppu_v ^= 0x0400;
-            } else {
-                self.ppu_v += 1
+        match dot {
+            256 => self.increment_y(),
+            257 => self.ppu_v = (self.ppu_v & 0xFBE0) | (self.ppu_t & 0x041F),
+            280..=304 if pre_render_line => {
+                self.ppu_v = (self.ppu_v & 0x841F) | (self.ppu_t & 0x7BE0);
             }
+            339 => {
+                self.read_ppu_byte(0x2000 | (self.ppu_v & 0x0FFF));
+            }
+            _ => {}
+        }
+    }
+
+    //Returns the palette index (0 - 15, from 0x3F00) of the current dot, 0 if transparent
+    pub(super) fn get_background_pixel(&self, x: u8) -> u8 {
+        if (self.ppumask & BACKGROUND_ENABLED) == 0 {
+            return 0;
+        }
+        //The leftmost 8 pixels can be hidden
+        if x < 8 && (self.ppumask & BACKGROUD_LEFT_ENABLED) == 0 {
+            return 0;
+        }
+
+        let bit = 0x8000 >> self.ppu_x;
+        let pattern = u8::from((self.bg_pattern_shift_low & bit) != 0)
+            | (u8::from((self.bg_pattern_shift_high & bit) != 0) << 1);
+        if pattern == 0 {
+            return 0;
         }
-        //Increment
+        let palette = u8::from((self.bg_attribute_shift_low & bit) != 0)
+            | (u8::from((self.bg_attribute_shift_high & bit) != 0) << 1);
+        (palette << 2) | pattern
+    }
+
+    fn shift_background_registers(&mut self) {
+        self.bg_pattern_shift_low = self.bg_pattern_shift_low << 1;
+        self.bg_pattern_shift_high = self.bg_pattern_shift_high << 1;
+        self.bg_attribute_shift_low = self.bg_attribute_shift_low << 1;
+        self.bg_attribute_shift_high = self.bg_attribute_shift_high << 1;
+    }
 
+    //The fetched tile goes in the low byte, the high byte holds the tile being drawn
+    fn load_background_registers(&mut self) {
+        self.bg_pattern_shift_low =
+            (self.bg_pattern_shift_low & 0xFF00) | u16::from(self.bg_next_pattern_low);
+        self.bg_pattern_shift_high =
+            (self.bg_pattern_shift_high & 0xFF00) | u16::from(self.bg_next_pattern_high);
+
+        //The attribute bits are the same for the 8 pixels of the tile
+        let attribute_low = if self.bg_next_attribute & 0x1 != 0 {
+            0xFF
+        } else {
+            0x00
+        };
+        let attribute_high = if self.bg_next_attribute & 0x2 != 0 {
+            0xFF
+        } else {
+            0x00
+        };
+        self.bg_attribute_shift_low = (self.bg_attribute_shift_low & 0xFF00) | attribute_low;
+        self.bg_attribute_shift_high = (self.bg_attribute_shift_high & 0xFF00) | attribute_high;
+    }
+
+    //Address of the pattern low plane of the next tile, for the fine Y in v
+    fn get_background_tile_address(&mut self) -> u16 {
+        let fine_y = (self.ppu_v >> 12) & 0x7;
+        self.get_active_pattern_table(BACKGROUND_PATTERN_TABLE)
+            //A tile is 16 bytes, so the address of the tile is pattern_table + (tile_index * 16)
+            .wrapping_add(u16::from(self.bg_next_tile_index) << 4)
+            //Each byte represents a row
+            .wrapping_add(fine_y)
+    }
+
+    fn increment_coarse_x(&mut self) {
+        if (self.ppu_v & 0x001F) == 31 {
+            self.ppu_v &= !0x001F;
+            self.ppu_v ^= 0x0400;
+        } else {
+            self.ppu_v += 1
+        }
+    }
+
+    fn increment_y(&mut self) {
         if (self.ppu_v & 0x7000) != 0x7000 {
             self.ppu_v += 0x1000;
         } else {
@@ -75,8 +140,6 @@ impl Nes {
             }
             self.ppu_v = (self.ppu_v & !0x03E0) | (y << 5)
         }
-
-        self.ppu_v = (self.ppu_v & 0xFBE0) | (u16::from(self.ppu_t & 0x041F));
     }
 
     fn retrieve_attribute_table_value(&mut self, nametable_tile_address: u16) -> u8 {
@@ -92,19 +155,4 @@ impl Nes {
 
         return (attribute_table_entry >> (internal_group_index * 2)) & 0x3;
     }
-
-    fn get_tile_row_pixels(
-        palette_msb: u8,
-        tile_first_plane: u8,
-        tile_second_plane: u8,
-    ) -> [u8; 8] {
-        let mut pixels = [0x0 as u8; 8];
-
-        for i in 0..=7 {
-            let palette_lsb =
-                (tile_first_plane >> (7 - i) & 0x1) | ((tile_second_plane >> (7 - i) & 0x1) << 1);
-            pixels[i] = (palette_msb << 2) | (palette_lsb as u8);
-        }
-        return pixels;
-    }
 }
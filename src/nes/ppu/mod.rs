@@ -1,4 +1,6 @@
-use crate::nes::ppu::registers::{BACKGROUND_ENABLED, SPRITES_ENABLED, SPRITE_0_HIT, V_BLANK};
+use crate::nes::ppu::registers::{
+    BACKGROUND_ENABLED, NMI_ENABLED, SPRITES_ENABLED, SPRITE_0_HIT, SPRITE_OVERFLOW, V_BLANK,
+};
 use crate::nes::Nes;
 
 mod background_renderer;
@@ -8,49 +10,162 @@ pub(crate) mod registers;
 mod sprite_renderer;
 mod utilities;
 
+pub(crate) use sprite_renderer::{SpritePixel, TRANSPARENT_SPRITE_PIXEL};
+
 impl Nes {
     pub fn execute_ppu(&mut self, cpu_cycles: u32) {
+        //Part of the instruction may have already been run by sync_ppu
+        let cpu_cycles = cpu_cycles.saturating_sub(self.ppu_synced_cycles);
+        self.ppu_synced_cycles = 0;
+        self.run_ppu(cpu_cycles, true);
+    }
+
+    //Catches up the PPU with the CPU cycles spent by the current instruction before
+    //the bus access in progress, for registers whose value depends on the exact dot
+    pub(crate) fn sync_ppu(&mut self) {
+        let elapsed_cycles = self.cpu_bus_accesses.saturating_sub(1);
+        let pending_cycles = elapsed_cycles.saturating_sub(self.ppu_synced_cycles);
+        self.ppu_synced_cycles += pending_cycles;
+        self.run_ppu(pending_cycles, false);
+    }
+
+    //instruction_end is set when the cycles run up to the end of the current instruction
+    fn run_ppu(&mut self, cpu_cycles: u32, instruction_end: bool) {
         //A CPU tick is equal to 3 PPU ticks...
         let ppu_cycles = cpu_cycles * 3;
-        let clock_current_scanline = self.clock_current_scanline.wrapping_add(ppu_cycles);
-
-        self.clock_current_scanline = clock_current_scanline;
-        if clock_current_scanline >= 341 {
-            match self.current_scanline {
-                0..=239 => {
-                    //TODO maybe I should emulate the PPU clock by clock?
-                    if (self.ppumask & BACKGROUND_ENABLED) != 0 {
-                        self.render_background(self.current_scanline as u16);
-                    }
-                    if (self.ppumask & SPRITES_ENABLED) != 0 {
-                        self.render_sprites(self.current_scanline as u16);
-                    }
-                    self.current_scanline += 1;
+        for tick in 0..ppu_cycles {
+            //An edge during the last CPU cycle of the instruction delays the NMI
+            let last_cpu_cycle = instruction_end && ppu_cycles - tick <= 3;
+            self.run_ppu_dot(last_cpu_cycle);
+        }
+    }
+
+    /*
+    A frame is 262 lines of 341 dots:
+    - 0-239: visible lines, a pixel per dot in 1-256
+    - 240: idle
+    - 241-260: VBlank, set on dot 1 of line 241
+    - 261: pre-render line, same memory accesses of a visible line without drawing.
+      On odd frames its last dot is skipped when rendering is enabled
+     */
+    fn run_ppu_dot(&mut self, last_cpu_cycle: bool) {
+        let line = self.current_scanline;
+        let dot = self.clock_current_scanline;
+        let rendering_enabled = (self.ppumask & (BACKGROUND_ENABLED | SPRITES_ENABLED)) != 0;
+
+        match line {
+            0..=239 | 261 => {
+                let pre_render_line = line == 261;
+                if pre_render_line && dot == 1 {
+                    self.ppustatus = self.ppustatus & !(V_BLANK | SPRITE_0_HIT | SPRITE_OVERFLOW);
+                    self.update_nmi_output(false);
+                }
+                if rendering_enabled {
+                    self.run_background_dot(dot, pre_render_line);
                 }
-                240 => {
-                    //set VBlank, check if NMI is active and raise
-                    let mut ppustatus = self.ppustatus;
-                    ppustatus = ppustatus | V_BLANK;
-                    ppustatus = ppustatus & !SPRITE_0_HIT;
-                    self.ppustatus = ppustatus;
-
-                    self.current_scanline += 1;
+                if dot == 257 {
+                    self.clear_sprite_line();
                 }
-                //VBlank = do nothing
-                241..=260 => {
-                    self.current_scanline += 1;
+                if rendering_enabled {
+                    self.run_sprite_dot(dot, pre_render_line);
                 }
-                //Finished scanlines, reset
-                261 => {
-                    self.current_scanline = 0;
-                    self.ppustatus = self.ppustatus & !V_BLANK;
+                if !pre_render_line && (1..=256).contains(&dot) {
+                    self.render_dot_pixel((dot - 1) as u8, line as u8, rendering_enabled);
                 }
-                _ => {
-                    //TODO panic
-                    panic!("Bad scanline")
+            }
+            241 if dot == 1 => {
+                //set VBlank, unless PPUSTATUS has been read just before
+                if !self.suppress_vblank {
+                    self.ppustatus = self.ppustatus | V_BLANK;
                 }
+                self.suppress_vblank = false;
+                self.update_nmi_output(last_cpu_cycle);
+                self.frame_count += 1;
+            }
+            _ => {}
+        }
+
+        self.clock_current_scanline += 1;
+        if line == 261 && dot == 339 && rendering_enabled && self.odd_frame {
+            self.clock_current_scanline = 341;
+        }
+        if self.clock_current_scanline == 341 {
+            self.clock_current_scanline = 0;
+            self.current_scanline += 1;
+            if self.current_scanline == 262 {
+                self.current_scanline = 0;
+                self.odd_frame = !self.odd_frame;
             }
-            self.clock_current_scanline -= 341;
         }
     }
+
+    //Output of the pixel multiplexer: background or sprite pixel, depending on their priority
+    fn render_dot_pixel(&mut self, x: u8, y: u8, rendering_enabled: bool) {
+        if !rendering_enabled {
+            //The backdrop color is shown, unless v points to a palette entry
+            let palette_address = if (self.ppu_v & 0x3F00) == 0x3F00 {
+                self.ppu_v
+            } else {
+                0x3F00
+            };
+            self.render_pixel(palette_address, x, y);
+            return;
+        }
+
+        let background = self.get_background_pixel(x);
+        let sprite = self.get_sprite_pixel(x);
+        let background_opaque = (background & 0x3) != 0;
+        let sprite_opaque = (sprite.palette_index & 0x3) != 0;
+
+        //Clipped pixels are transparent, so no hit there. There is never a hit on the last pixel
+        if sprite.sprite_zero && background_opaque && x != 255 {
+            self.ppustatus = self.ppustatus | SPRITE_0_HIT;
+        }
+
+        let palette_address = match (background_opaque, sprite_opaque) {
+            (false, false) => 0x3F00,
+            (true, false) => 0x3F00 + u16::from(background),
+            (false, true) => 0x3F10 + u16::from(sprite.palette_index),
+            (true, true) => {
+                if sprite.behind_background {
+                    0x3F00 + u16::from(background)
+                } else {
+                    0x3F10 + u16::from(sprite.palette_index)
+                }
+            }
+        };
+        self.render_pixel(palette_address, x, y);
+    }
+
+    /*
+    The NMI line of the PPU is VBlank AND NMI_ENABLED. The CPU detects its rising edge,
+    so toggling NMI_ENABLED during VBlank raises another NMI.
+    If the edge happens during the last cycle of an instruction, the CPU has already polled
+    for interrupts and the NMI is serviced after the next instruction.
+     */
+    pub(crate) fn update_nmi_output(&mut self, delayed: bool) {
+        let nmi_output = (self.ppuctrl & NMI_ENABLED) != 0 && (self.ppustatus & V_BLANK) != 0;
+        if nmi_output && !self.nmi_output {
+            self.nmi_pending = true;
+            self.nmi_delayed = delayed;
+        }
+        self.nmi_output = nmi_output;
+    }
+
+    pub(crate) fn read_ppustatus(&mut self) -> u8 {
+        self.sync_ppu();
+        match (self.current_scanline, self.clock_current_scanline) {
+            //One dot before VBlank: the flag reads clear and won't be set in this frame
+            (241, 1) => self.suppress_vblank = true,
+            //On the first dots of VBlank: the flag reads set, but the NMI is cancelled
+            (241, 2..=3) => self.nmi_pending = false,
+            _ => {}
+        }
+
+        self.ppu_second_write = false;
+        let ret_val = self.ppustatus;
+        self.ppustatus = self.ppustatus & !V_BLANK;
+        self.update_nmi_output(false);
+        ret_val
+    }
 }
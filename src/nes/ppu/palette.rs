@@ -8,3 +8,41 @@ pub(crate) const NES_PALETTE: [u32; 64] = [
     0xFFFFFF00, 0xABE7FF00, 0xC7D7FF00, 0xD7CBFF00, 0xFFC7FF00, 0xFFC7DB00, 0xFFBFB300, 0xFFDBAB00,
     0xFFE7A300, 0xE3FFA300, 0xABF3BF00, 0xB3FFCF00, 0x9FFFF300, 0x00000000, 0x00000000, 0x00000000,
 ];
+
+//Each emphasis bit darkens the other two color components to about 3/4
+const EMPHASIS_ATTENUATION: u32 = 746;
+
+/*
+NES_PALETTE for each of the 8 combinations of the emphasis bits: the index is EEEC CCCC,
+where E is BGR (the emphasis bits of PPUMASK, red and green already swapped on PAL)
+ */
+pub(crate) const NES_PALETTE_EMPHASIS: [u32; 512] = build_emphasis_palette();
+
+const fn build_emphasis_palette() -> [u32; 512] {
+    let mut palette = [0; 512];
+    let mut emphasis = 0;
+    while emphasis < 8 {
+        let mut color = 0;
+        while color < 64 {
+            let rgb = NES_PALETTE[color];
+            let mut channels = [(rgb >> 24) & 0xFF, (rgb >> 16) & 0xFF, (rgb >> 8) & 0xFF];
+            let mut channel = 0;
+            while channel < 3 {
+                //A channel is attenuated by every emphasis bit, except its own
+                let mut bit = 0;
+                while bit < 3 {
+                    if bit != channel && (emphasis & (1 << bit)) != 0 {
+                        channels[channel] = channels[channel] * EMPHASIS_ATTENUATION / 1000;
+                    }
+                    bit += 1;
+                }
+                channel += 1;
+            }
+            palette[(emphasis << 6) | color] =
+                (channels[0] << 24) | (channels[1] << 16) | (channels[2] << 8);
+            color += 1;
+        }
+        emphasis += 1;
+    }
+    palette
+}
@@ -1,128 +1,234 @@
 use crate::nes::ppu::registers::{
-    SPRITE_0_HIT, SPRITE_OVERFLOW, SPRITE_PATTERN_TABLE, SPRITE_SIZE_16,
+    SPRITES_ENABLED, SPRITE_LEFT_ENABLED, SPRITE_OVERFLOW, SPRITE_PATTERN_TABLE, SPRITE_SIZE_16,
 };
 use crate::Nes;
-use std::ops::Mul;
 
-impl Nes {
-    pub(super) fn render_sprites(&mut self, current_scanline: u16) {
-        // PPU has a 32 byte memory that works as a secondary OAM that contains
-        // the 8 sprites for this line. We start by doing a linear search
+//Pixel of the sprite line buffer, filled while the sprites of the next line are fetched
+#[derive(Copy, Clone)]
+pub(crate) struct SpritePixel {
+    //Palette index (0 - 15, from 0x3F10), transparent if the 2 low bits are 0
+    pub(super) palette_index: u8,
+    pub(super) behind_background: bool,
+    //The pixel comes from sprite 0
+    pub(super) sprite_zero: bool,
+}
 
-        let mut secondary_oam: [u8; 8] = [0; 8];
-        let mut secondary_oam_index = 0;
-        //Sprites can be 8x8 or 8x16, based on PPUCTRL
-        let sprite_size = self.get_sprite_size();
-        for i in 0..=63 {
-            // A sprite is composed by 4 bytes:
-            // Byte 0 = y position - 1
-            // Byte 1 = tile number
-            // Byte 2 = Attributes
-            // Byte 3 = x position
-
-            let current_sprite_y_index = i * 4;
-            let y_pos = (self.oam_ram[current_sprite_y_index] as u16).wrapping_add(1);
-
-            if current_scanline >= y_pos && current_scanline < (y_pos + sprite_size) {
-                if secondary_oam_index == 8 {
-                    //There are more than 8 sprites on this line -> sprite overflow
-                    self.ppustatus = self.ppustatus | SPRITE_OVERFLOW;
-                } else {
-                    secondary_oam[secondary_oam_index] = current_sprite_y_index as u8;
-                    secondary_oam_index += 1;
+pub(crate) const TRANSPARENT_SPRITE_PIXEL: SpritePixel = SpritePixel {
+    palette_index: 0,
+    behind_background: false,
+    sprite_zero: false,
+};
+
+impl Nes {
+    /*
+    Sprite pipeline, run on every dot of the visible and pre-render lines while rendering:
+    - dots 1-64: secondary OAM is cleared to 0xFF
+    - dots 65-256: sprite evaluation, looks for the sprites of the next line (not on pre-render)
+    - dots 257-320: for each of the 8 slots, two garbage nametable fetches, then pattern low
+      and pattern high. The pixels of the sprite go in the sprite line buffer
+    The sprites found on a line are drawn on the next one, so line 0 never has sprites
+     */
+    pub(super) fn run_sprite_dot(&mut self, dot: u32, pre_render_line: bool) {
+        match dot {
+            1 => {
+                self.secondary_oam = [0xFF; 32];
+                self.sprite_count = 0;
+                self.sprite_eval_index = 0;
+                self.sprite_eval_byte = 0;
+                self.sprite_zero_in_line = false;
+            }
+            65..=256 if !pre_render_line => {
+                //Each sprite takes 2 dots to be checked
+                if dot % 2 == 1 {
+                    self.evaluate_next_sprite();
+                }
+            }
+            257..=320 => {
+                let slot = ((dot - 257) / 8) as usize;
+                match (dot - 257) % 8 {
+                    0 | 2 => {
+                        self.read_ppu_byte(0x2000 | (self.ppu_v & 0x0FFF));
+                    }
+                    4 => {
+                        let address = self.get_sprite_slot_address(slot);
+                        self.sprite_pattern_low = self.read_ppu_byte(address);
+                    }
+                    6 => {
+                        let address = self.get_sprite_slot_address(slot);
+                        let tile_second_plane = self.read_ppu_byte(address.wrapping_add(8));
+                        if slot < self.sprite_count {
+                            self.draw_sprite_slot(slot, self.sprite_pattern_low, tile_second_plane);
+                        }
+                    }
+                    _ => {}
                 }
             }
+            _ => {}
         }
-        let number_sprites_scanline = secondary_oam_index;
-        //TODO handle sprite 0 hit
-        for i in 0..number_sprites_scanline {
-            let sprite_index = secondary_oam[i] as usize;
-            let sprite_y_position = self.oam_ram[sprite_index];
-            let sprite_tile_index = self.oam_ram[sprite_index + 1];
-            let sprite_attributes = self.oam_ram[sprite_index + 2];
-            let sprite_x_position = self.oam_ram[sprite_index + 3];
-
-            //If 0, the sprite is in front at the backgroud
-            let sprite_priority = (sprite_attributes & 0x20) == 0;
-
-            let pattern_table: u16 =
-                // If sprite_size is 8, the pattern table depends of PPUCTRL bit
-                // If sprite_size if 16, the LSB of the index indicates the table
-                if sprite_size == 8 {
-                    self.get_active_pattern_table(SPRITE_PATTERN_TABLE)
-                } else {
-                    (u16::from(sprite_tile_index) & 0x1) * 0x1000
-                };
-
-            let tile_address = if sprite_size == 8 {
-                pattern_table.wrapping_add(u16::from(sprite_tile_index) << 4)
-            } else {
-                pattern_table.wrapping_add(u16::from(sprite_tile_index & 0xFE) << 4)
-            };
+    }
+
+    pub(super) fn clear_sprite_line(&mut self) {
+        self.sprite_line = [TRANSPARENT_SPRITE_PIXEL; 256];
+    }
+
+    pub(super) fn get_sprite_pixel(&self, x: u8) -> SpritePixel {
+        if (self.ppumask & SPRITES_ENABLED) == 0 {
+            return TRANSPARENT_SPRITE_PIXEL;
+        }
+        //The leftmost 8 pixels can be hidden
+        if x < 8 && (self.ppumask & SPRITE_LEFT_ENABLED) == 0 {
+            return TRANSPARENT_SPRITE_PIXEL;
+        }
+        self.sprite_line[x as usize]
+    }
+
+    // PPU has a 32 byte memory that works as a secondary OAM that contains
+    // the 8 sprites for the next line, copied from OAM in the same order
+    fn evaluate_next_sprite(&mut self) {
+        if self.sprite_eval_index >= 64 {
+            return;
+        }
+        // A sprite is composed by 4 bytes:
+        // Byte 0 = y position - 1
+        // Byte 1 = tile number
+        // Byte 2 = Attributes
+        // Byte 3 = x position
+        let current_sprite_y_index = self.sprite_eval_index * 4;
+
+        if self.sprite_count == 8 {
+            self.evaluate_overflow_sprite();
+            return;
+        }
+        self.sprite_eval_index += 1;
+
+        let y_pos = u32::from(self.oam_ram[current_sprite_y_index]);
+        if self.is_sprite_in_next_line(y_pos) {
+            let slot_index = self.sprite_count * 4;
+            self.secondary_oam[slot_index..slot_index + 4]
+                .copy_from_slice(&self.oam_ram[current_sprite_y_index..current_sprite_y_index + 4]);
+            if current_sprite_y_index == 0 {
+                self.sprite_zero_in_line = true;
+            }
+            self.sprite_count += 1;
+        }
+    }
 
-            let current_tile_row = current_scanline
-                .wrapping_sub(u16::from(sprite_y_position))
-                .wrapping_sub(1);
+    //Once 8 sprites are found, the PPU keeps looking for a 9th one to set the overflow flag.
+    //Because of a hardware bug, the byte index m is incremented together with the sprite index n
+    //when a sprite isn't on the line, so the tile, attribute and x bytes are read as y positions.
+    //This gives both false positives and false negatives
+    fn evaluate_overflow_sprite(&mut self) {
+        let y_pos = u32::from(self.oam_ram[self.sprite_eval_index * 4 + self.sprite_eval_byte]);
+        if self.is_sprite_in_next_line(y_pos) {
+            //There are more than 8 sprites on the next line -> sprite overflow.
+            //The flag can't change any more until the end of the line
+            self.ppustatus = self.ppustatus | SPRITE_OVERFLOW;
+            self.sprite_eval_index = 64;
+        } else {
+            self.sprite_eval_index += 1;
+            self.sprite_eval_byte = (self.sprite_eval_byte + 1) & 0x3;
+        }
+    }
 
-            let row_to_draw = if sprite_attributes & 0x80 == 0 {
-                current_tile_row
+    fn is_sprite_in_next_line(&self, y_pos: u32) -> bool {
+        let row = self.current_scanline.wrapping_sub(y_pos);
+        row < u32::from(self.get_sprite_size())
+    }
+
+    //Address of the pattern low plane for the row of the next line.
+    //The PPU always fetches 8 sprites per line, the unused slots load tile 0xFF.
+    //Nothing is drawn, but mappers like MMC3 watch these accesses on the PPU bus
+    fn get_sprite_slot_address(&mut self, slot: usize) -> u16 {
+        //Sprites can be 8x8 or 8x16, based on PPUCTRL
+        let sprite_size = self.get_sprite_size();
+        if slot >= self.sprite_count {
+            return if sprite_size == 8 {
+                self.get_active_pattern_table(SPRITE_PATTERN_TABLE) | 0x0FF0
             } else {
-                (sprite_size - 1) - current_tile_row
+                0x1FE0
             };
+        }
+
+        let slot_index = slot * 4;
+        let sprite_y_position = self.secondary_oam[slot_index];
+        let sprite_tile_index = self.secondary_oam[slot_index + 1];
+        let sprite_attributes = self.secondary_oam[slot_index + 2];
 
-            let sprite_size_adj = if row_to_draw >= 8 && sprite_size == 16 {
-                row_to_draw.wrapping_add(8)
+        let pattern_table: u16 =
+            // If sprite_size is 8, the pattern table depends of PPUCTRL bit
+            // If sprite_size if 16, the LSB of the index indicates the table
+            if sprite_size == 8 {
+                self.get_active_pattern_table(SPRITE_PATTERN_TABLE)
             } else {
-                row_to_draw
+                (u16::from(sprite_tile_index) & 0x1) * 0x1000
             };
 
-            let tile_row_address = tile_address.wrapping_add(sprite_size_adj);
-            let tile_first_plane = self.read_ppu_byte(tile_row_address);
-            let tile_second_plane = self.read_ppu_byte(tile_row_address.wrapping_add(8));
-
-            let palette_msb = sprite_attributes & 0x3;
-
-            for current_pixel in 0..=7 {
-                //To handle pixel mirroring
-                let pixel_to_render = if sprite_attributes & 0x40 == 0 {
-                    current_pixel
-                } else {
-                    7 - current_pixel
-                };
-
-                let palette_lsb = Nes::get_tile_pixel_from_planes(
-                    tile_first_plane,
-                    tile_second_plane,
-                    pixel_to_render,
-                );
-
-                if palette_lsb == 0x0 {
-                    //Transparent pixel -> nothing to do here
-                    continue;
-                }
+        let tile_address = if sprite_size == 8 {
+            pattern_table.wrapping_add(u16::from(sprite_tile_index) << 4)
+        } else {
+            pattern_table.wrapping_add(u16::from(sprite_tile_index & 0xFE) << 4)
+        };
 
-                let palette_index = (palette_msb << 2) | (palette_lsb as u8);
-                let x_pos = u16::from(sprite_x_position).wrapping_add(u16::from(current_pixel));
+        //The sprite is drawn on the next line
+        let current_tile_row =
+            (self.current_scanline as u16).wrapping_sub(u16::from(sprite_y_position));
 
-                if x_pos < 256 {
-                    let index_screen = u16::from(current_scanline)
-                        .mul(256)
-                        .wrapping_add(u16::from(x_pos));
+        let row_to_draw = if sprite_attributes & 0x80 == 0 {
+            current_tile_row
+        } else {
+            (sprite_size - 1) - current_tile_row
+        };
 
-                    let has_background = self.background_hit_flag[index_screen as usize];
+        let sprite_size_adj = if row_to_draw >= 8 && sprite_size == 16 {
+            row_to_draw.wrapping_add(8)
+        } else {
+            row_to_draw
+        };
 
-                    if sprite_index == 0 && has_background {
-                        self.ppustatus = self.ppustatus | SPRITE_0_HIT;
-                    }
+        tile_address.wrapping_add(sprite_size_adj)
+    }
 
-                    //I must draw the pixel only if there isn't a background pixel or if the sprite is in front background
-                    if !has_background || sprite_priority {
-                        self.render_pixel(
-                            0x3F10 + u16::from(palette_index),
-                            x_pos as u8,
-                            current_scanline as u8,
-                        );
-                    }
+    fn draw_sprite_slot(&mut self, slot: usize, tile_first_plane: u8, tile_second_plane: u8) {
+        let slot_index = slot * 4;
+        let sprite_attributes = self.secondary_oam[slot_index + 2];
+        let sprite_x_position = self.secondary_oam[slot_index + 3];
+        //Sprite 0 can only be in the first slot
+        let sprite_zero = slot == 0 && self.sprite_zero_in_line;
+
+        //If 0, the sprite is in front at the backgroud
+        let behind_background = (sprite_attributes & 0x20) != 0;
+        let palette_msb = sprite_attributes & 0x3;
+
+        for current_pixel in 0..=7 {
+            //To handle pixel mirroring
+            let pixel_to_render = if sprite_attributes & 0x40 == 0 {
+                current_pixel
+            } else {
+                7 - current_pixel
+            };
+
+            let palette_lsb = Nes::get_tile_pixel_from_planes(
+                tile_first_plane,
+                tile_second_plane,
+                pixel_to_render,
+            );
+
+            if palette_lsb == 0x0 {
+                //Transparent pixel -> nothing to do here
+                continue;
+            }
+
+            let x_pos = usize::from(sprite_x_position) + usize::from(current_pixel);
+            if x_pos < 256 {
+                //The slots are fetched in OAM order, so the lowest OAM index wins the pixel.
+                //A sprite behind the background still hides the sprites after it
+                let pixel = &mut self.sprite_line[x_pos];
+                if (pixel.palette_index & 0x3) == 0 {
+                    *pixel = SpritePixel {
+                        palette_index: (palette_msb << 2) | palette_lsb,
+                        behind_background,
+                        sprite_zero,
+                    };
                 }
             }
         }
@@ -1,5 +1,5 @@
-use crate::nes::ppu::palette::NES_PALETTE;
-use crate::nes::ppu::registers::BACKGROUND_PATTERN_TABLE;
+use crate::nes::ppu::palette::NES_PALETTE_EMPHASIS;
+use crate::nes::ppu::registers::{BLUE_EMPHASIZE, GREEN_EMPHASIZE, GREYSCALE, RED_EMPHASIZE};
 use crate::Nes;
 use std::ops::Mul;
 
@@ -12,27 +12,37 @@ impl Nes {
         }
     }
 
-    pub(super) fn retrieve_tile_row(&mut self, tile_index: u8, y: u8) -> (u8, u8) {
-        let pattern_table: u16 = self.get_active_pattern_table(BACKGROUND_PATTERN_TABLE);
-
-        let tile_address = pattern_table
-            //A tile is 16 bytes, so the address of the tile is pattern_table + (tile_index * 16)
-            .wrapping_add(u16::from(tile_index) << 4)
-            //Each byte represents a row
-            .wrapping_add(u16::from(y));
-
-        //Each tile row is composed by 2 bytes
-        let tile_first_plane = self.read_ppu_byte(tile_address);
-        let tile_second_plane = self.read_ppu_byte(tile_address.wrapping_add(8));
-        return (tile_first_plane, tile_second_plane);
+    //Emphasis bits as BGR, PAL and Dendy PPUs swap the red and green bits of PPUMASK
+    fn get_color_emphasis(&self) -> usize {
+        let (red_bit, green_bit) = if self.swap_red_green_emphasis {
+            (GREEN_EMPHASIZE, RED_EMPHASIZE)
+        } else {
+            (RED_EMPHASIZE, GREEN_EMPHASIZE)
+        };
+        let mut emphasis = 0;
+        if (self.ppumask & red_bit) != 0 {
+            emphasis = emphasis | 0b001;
+        }
+        if (self.ppumask & green_bit) != 0 {
+            emphasis = emphasis | 0b010;
+        }
+        if (self.ppumask & BLUE_EMPHASIZE) != 0 {
+            emphasis = emphasis | 0b100;
+        }
+        emphasis
     }
 
     pub(super) fn render_pixel(&mut self, palette_address: u16, x: u8, y: u8) {
         // Nes palettes are 6 bit and the PPU only uses 6 bits to retrieve the value from
         // the system palette
-        let palette_for_pixel = self.read_ppu_byte(palette_address) & 0x3F;
+        let mut palette_for_pixel = self.read_palette_byte(palette_address) & 0x3F;
+        //Greyscale keeps only the grey column of the palette
+        if (self.ppumask & GREYSCALE) != 0 {
+            palette_for_pixel = palette_for_pixel & 0x30;
+        }
 
-        let rgb_color = NES_PALETTE[palette_for_pixel as usize];
+        let emphasis = self.get_color_emphasis();
+        let rgb_color = NES_PALETTE_EMPHASIS[(emphasis << 6) | palette_for_pixel as usize];
 
         //Nes screen is 256x240
         let index_screen = u16::from(y).mul(256).wrapping_add(u16::from(x));
@@ -5,4 +5,15 @@ impl Nes {
     pub fn set_controller_status(&mut self, button: NesControllerButton, is_pressed: bool) {
         self.controller_first_port[button as usize] = is_pressed;
     }
+
+    pub(crate) fn read_controller_port(&mut self) -> u8 {
+        return match self.first_port_strobing_index {
+            0..=7 => {
+                let is_pressed = self.controller_first_port[self.first_port_strobing_index];
+                self.first_port_strobing_index += 1;
+                return if is_pressed { 0x1 } else { 0x0 };
+            }
+            _ => 0,
+        };
+    }
 }
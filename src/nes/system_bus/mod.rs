@@ -1,10 +1,11 @@
-use crate::nes::ppu::registers::{VRAM_INCREMENT, V_BLANK};
+use crate::nes::ppu::registers::VRAM_INCREMENT;
 use crate::nes::Nes;
 
 mod controller;
 
 impl Nes {
     pub(crate) fn read_cpu_byte(&mut self, addr: u16) -> u8 {
+        self.cpu_bus_accesses += 1;
         return match addr {
             0x0000..=0x1FFF => {
                 //Ram is mirrored in this space
@@ -17,12 +18,7 @@ impl Nes {
                 return match ppu_io_addr {
                     //todo should panic?
                     0 | 1 | 3 | 5 | 6 => 0,
-                    2 => {
-                        self.ppu_second_write = false;
-                        let ret_val = self.ppustatus;
-                        self.ppustatus = self.ppustatus & !V_BLANK;
-                        ret_val
-                    }
+                    2 => self.read_ppustatus(),
                     4 => {
                         let oam_addr = self.oam_addr;
                         self.oam_ram[oam_addr as usize]
@@ -50,24 +46,18 @@ impl Nes {
             //DMA request, only write
             0x4014 => 0,
             //APU status
-            0x4015 => 0,
+            0x4015 => self.read_apu_status(),
             //Joypad 1 and strobing
             0x4016 => {
-                return match self.first_port_strobing_index {
-                    0..=7 => {
-                        let is_pressed = self.controller_first_port[self.first_port_strobing_index];
-                        self.first_port_strobing_index += 1;
-                        return if is_pressed { 0x1 } else { 0x0 };
-                    }
-                    _ => 0,
-                };
+                self.controller_read_cycle = Some(self.cpu_bus_accesses - 1);
+                self.read_controller_port()
             }
             //Joypad 2
             0x4017 => 0,
             //Used only on debug, disabled on commercial NES
             0x4018..=0x401F => 0,
             //Expansion ROM (only certain mappers)
-            0x4020..=0x5FFF => 0,
+            0x4020..=0x5FFF => self.cartridge.read_expansion_byte(addr),
             //Cart RAM
             0x6000..=0x7FFF => self.cartridge.read_ram_byte(addr - 0x6000),
             0x8000..=0xFFFF => self.cartridge.read_pkg_byte(addr - 0x8000),
@@ -75,6 +65,7 @@ impl Nes {
     }
 
     pub(crate) fn write_cpu_byte(&mut self, addr: u16, value: u8) {
+        self.cpu_bus_accesses += 1;
         match addr {
             0x0000..=0x1FFF => {
                 //Ram is mirrored in this space
@@ -88,7 +79,9 @@ impl Nes {
                 return match ppu_io_addr {
                     0 => {
                         self.ppu_t = (self.ppu_t & 0xF3FF) | ((u16::from(value) & 0x3) << 10);
-                        self.ppuctrl = value
+                        self.ppuctrl = value;
+                        //Enabling NMI during VBlank raises it, after the next instruction
+                        self.update_nmi_output(true);
                     }
                     1 => {
                         self.ppumask = value;
@@ -122,6 +115,8 @@ impl Nes {
                         } else {
                             self.ppu_t = (self.ppu_t & 0xFF00) | u16::from(value);
                             self.ppu_v = self.ppu_t;
+                            //Outside rendering the PPU address bus shows v
+                            self.cartridge.notify_ppu_address(self.ppu_v & 0x3FFF);
                         }
                         self.ppu_second_write = !second_write
                     }
@@ -141,25 +136,25 @@ impl Nes {
                 };
             }
             //APU I/O
-            0x4000..=0x4013 => {}
+            0x4000..=0x4013 => self.write_apu_register(addr, value),
             //DMA request
             0x4014 => {
                 self.request_dma = true;
                 self.dma_src = u16::from(value) << 8;
             }
             //APU status
-            0x4015 => {}
+            0x4015 => self.write_apu_status(value),
             //Joypad 1 and strobing
             0x4016 => {
                 self.first_port_strobing = value & 0x1 != 0;
                 self.first_port_strobing_index = 0;
             }
-            //Joypad 2
-            0x4017 => {}
+            //APU frame counter (joypad 2 has no strobe of its own)
+            0x4017 => self.write_apu_frame_counter(value),
             //Used only on debug, disabled on commercial NES
             0x4018..=0x401F => {}
-            //Expansion ROM (only certain mappers
-            0x4020..=0x5FFF => {}
+            //Expansion ROM (only certain mappers)
+            0x4020..=0x5FFF => self.cartridge.write_expansion_byte(addr, value),
             //Cart RAM
             0x6000..=0x7FFF => self.cartridge.write_ram_byte(addr - 0x6000, value),
             //PGR_ROM
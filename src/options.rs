@@ -0,0 +1,94 @@
+use crate::pacing::PacingSource;
+
+pub const USAGE: &str = "Usage: panznes [OPTIONS] ROM_FILE
+  --volume 0-100                Master volume (default 100)
+  --mute                        Disable the audio output
+  --sync audio|vsync|timer      What paces the emulation
+  --record-audio FILE.wav       Record the APU output from the start (F12 toggles it)
+  --record-channels             Also record each channel in FILE_<channel>.wav
+  --headless                    Run without window and audio output
+  --frames N                    Number of frames to run (required by --headless)";
+
+//Command line options
+pub struct Options {
+    pub rom_path: String,
+    //Master volume, between 0.0 and 1.0
+    pub volume: f32,
+    pub mute: bool,
+    //None lets the frontend choose (audio if available, or timer)
+    pub sync: Option<PacingSource>,
+    pub record_audio: Option<String>,
+    pub record_channels: bool,
+    pub headless: bool,
+    pub frames: Option<u64>,
+}
+
+impl Options {
+    //args includes the program name, like env::args()
+    pub fn parse(args: &[String]) -> Result<Options, String> {
+        let mut rom_path = None;
+        let mut volume = 1.0;
+        let mut mute = false;
+        let mut sync = None;
+        let mut record_audio = None;
+        let mut record_channels = false;
+        let mut headless = false;
+        let mut frames = None;
+
+        let mut args = args.iter().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--volume" => {
+                    let value = args.next().ok_or("Missing value for --volume")?;
+                    volume = match value.parse::<u32>() {
+                        Ok(percent) if percent <= 100 => percent as f32 / 100.0,
+                        _ => return Err(format!("Bad volume {}, expected 0-100", value)),
+                    };
+                }
+                "--mute" => mute = true,
+                "--sync" => {
+                    let value = args.next().ok_or("Missing value for --sync")?;
+                    sync = Some(PacingSource::from_name(value).ok_or(format!(
+                        "Bad sync {}, expected audio, vsync or timer",
+                        value
+                    ))?);
+                }
+                "--record-audio" => {
+                    let value = args.next().ok_or("Missing file for --record-audio")?;
+                    record_audio = Some(value.clone());
+                }
+                "--record-channels" => record_channels = true,
+                "--headless" => headless = true,
+                "--frames" => {
+                    let value = args.next().ok_or("Missing value for --frames")?;
+                    let count = value
+                        .parse::<u64>()
+                        .map_err(|_| format!("Bad number of frames {}", value))?;
+                    frames = Some(count);
+                }
+                _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+                _ => {
+                    if rom_path.is_some() {
+                        return Err(format!("Unexpected argument {}", arg));
+                    }
+                    rom_path = Some(arg.clone());
+                }
+            }
+        }
+
+        if headless && frames.is_none() {
+            return Err("--headless requires --frames".to_string());
+        }
+
+        Ok(Options {
+            rom_path: rom_path.ok_or("Missing ROM filename")?,
+            volume,
+            mute,
+            sync,
+            record_audio,
+            record_channels,
+            headless,
+            frames,
+        })
+    }
+}
@@ -0,0 +1,51 @@
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+//If the emulation is late by more than this (e.g. the window was dragged), it doesn't try to catch up
+const MAX_LATENESS: Duration = Duration::from_millis(100);
+
+//What decides when the next frame is emulated
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PacingSource {
+    //Waits for the audio queue to drain, the audio clock drives the emulation
+    Audio,
+    //Canvas presentation blocks until the display refresh
+    Vsync,
+    //Sleeps until the deadline of the next frame
+    Timer,
+}
+
+impl PacingSource {
+    pub fn from_name(name: &str) -> Option<PacingSource> {
+        return match name {
+            "audio" => Some(PacingSource::Audio),
+            "vsync" => Some(PacingSource::Vsync),
+            "timer" => Some(PacingSource::Timer),
+            _ => None,
+        };
+    }
+}
+
+pub struct FrameTimer {
+    next_deadline: Instant,
+}
+
+impl FrameTimer {
+    pub fn new() -> FrameTimer {
+        FrameTimer {
+            next_deadline: Instant::now(),
+        }
+    }
+
+    //frame_duration is the emulated time of the last frame. Deadlines are accumulated,
+    //so the sleep granularity doesn't make the emulation drift
+    pub fn wait(&mut self, frame_duration: Duration) {
+        self.next_deadline += frame_duration;
+        let now = Instant::now();
+        if self.next_deadline > now {
+            sleep(self.next_deadline - now);
+        } else if now - self.next_deadline > MAX_LATENESS {
+            self.next_deadline = now;
+        }
+    }
+}
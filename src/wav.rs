@@ -0,0 +1,111 @@
+use crate::nes::{ApuChannel, Nes, APU_CHANNELS};
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+const WAV_HEADER_SIZE: u32 = 44;
+
+//16 bits mono PCM WAV file. The sizes in the header are written by finish
+pub struct WavWriter {
+    writer: BufWriter<File>,
+    data_size: u32,
+}
+
+impl WavWriter {
+    pub fn create(path: &Path, sample_rate: u32) -> io::Result<WavWriter> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(b"WAVE")?;
+
+        writer.write_all(b"fmt ")?;
+        writer.write_all(&16u32.to_le_bytes())?;
+        //PCM, 1 channel
+        writer.write_all(&1u16.to_le_bytes())?;
+        writer.write_all(&1u16.to_le_bytes())?;
+        writer.write_all(&sample_rate.to_le_bytes())?;
+        //Byte rate and block align
+        writer.write_all(&(sample_rate * 2).to_le_bytes())?;
+        writer.write_all(&2u16.to_le_bytes())?;
+        writer.write_all(&16u16.to_le_bytes())?;
+
+        writer.write_all(b"data")?;
+        writer.write_all(&0u32.to_le_bytes())?;
+
+        Ok(WavWriter {
+            writer,
+            data_size: 0,
+        })
+    }
+
+    //The APU output is between 0.0 and 1.0, silence is 0
+    pub fn write_samples(&mut self, samples: &[f32]) -> io::Result<()> {
+        for &sample in samples {
+            let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.writer.write_all(&value.to_le_bytes())?;
+        }
+        self.data_size = self.data_size.saturating_add(samples.len() as u32 * 2);
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.writer.seek(SeekFrom::Start(4))?;
+        let riff_size = self.data_size.saturating_add(WAV_HEADER_SIZE - 8);
+        self.writer.write_all(&riff_size.to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(40))?;
+        self.writer.write_all(&self.data_size.to_le_bytes())?;
+        self.writer.flush()
+    }
+}
+
+//out.wav -> out_pulse1.wav
+fn get_channel_path(path: &Path, channel: ApuChannel) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}_{}.wav", stem, channel.get_name()))
+}
+
+//Records the mixed APU output at the CPU clock rate, before the frontend resampling and filters
+pub struct AudioRecorder {
+    mix: WavWriter,
+    channels: Vec<(ApuChannel, WavWriter)>,
+}
+
+impl AudioRecorder {
+    pub fn start(
+        path: &Path,
+        sample_rate: u32,
+        per_channel: bool,
+        nes: &mut Nes,
+    ) -> io::Result<AudioRecorder> {
+        let mix = WavWriter::create(path, sample_rate)?;
+        let mut channels = Vec::new();
+        if per_channel {
+            for channel in APU_CHANNELS {
+                let writer = WavWriter::create(&get_channel_path(path, channel), sample_rate)?;
+                channels.push((channel, writer));
+            }
+        }
+
+        nes.set_channel_capture(per_channel);
+        Ok(AudioRecorder { mix, channels })
+    }
+
+    //samples is the output of take_audio_samples, the single channels are read from nes
+    pub fn write(&mut self, samples: &[f32], nes: &mut Nes) -> io::Result<()> {
+        self.mix.write_samples(samples)?;
+        for (channel, writer) in self.channels.iter_mut() {
+            writer.write_samples(&nes.take_channel_samples(*channel))?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(self, nes: &mut Nes) -> io::Result<()> {
+        nes.set_channel_capture(false);
+        self.mix.finish()?;
+        for (_, writer) in self.channels {
+            writer.finish()?;
+        }
+        Ok(())
+    }
+}